@@ -1,9 +1,15 @@
 use criterion::{criterion_group, criterion_main, Criterion};
-use metrics::{common::KeyValue, meter_provider::MeterProvider};
+use metrics::{
+    common::KeyValue, exporter::StdoutExporter, meter_provider::MeterProvider,
+    noop::NoopMeterProvider, periodic_reader::PeriodicReader,
+};
+use std::time::Duration;
 
 // cargo bench --bench counter
 pub fn counter_benchmark(c: &mut Criterion) {
-    let meter_provider = MeterProvider::new_with_periodic_flush();
+    let reader =
+        PeriodicReader::new(StdoutExporter::default()).with_interval(Duration::from_secs(10));
+    let meter_provider = MeterProvider::new_with_readers(vec![Box::new(reader)], vec![]).unwrap();
     let meter = meter_provider.get_meter("meter");
     let counter = meter.create_counter("counter-name");
 
@@ -46,6 +52,22 @@ pub fn counter_benchmark(c: &mut Criterion) {
             counter.add(10, &attributes5);
         });
     });
+
+    let noop_counter = NoopMeterProvider::new()
+        .get_meter("meter")
+        .create_counter("counter-name");
+
+    c.bench_function("counter_noop_0", |b| {
+        b.iter(|| {
+            noop_counter.add(10, &[]);
+        });
+    });
+
+    c.bench_function("counter_noop_5", |b| {
+        b.iter(|| {
+            noop_counter.add(10, &attributes5);
+        });
+    });
 }
 
 criterion_group!(benches, counter_benchmark);
@@ -0,0 +1,305 @@
+use crate::error::{MetricsError, MetricsResult};
+use crate::instrument::InstrumentKind;
+
+/// Default bucket boundaries for explicit bucket histograms, as defined by the
+/// OpenTelemetry specification.
+pub const DEFAULT_HISTOGRAM_BOUNDARIES: [f64; 15] = [
+    0.0, 5.0, 10.0, 25.0, 50.0, 75.0, 100.0, 250.0, 500.0, 750.0, 1000.0, 2500.0, 5000.0, 7500.0,
+    10000.0,
+];
+
+pub(crate) const EXPONENTIAL_MAX_SIZE: u32 = 160;
+pub(crate) const EXPONENTIAL_MAX_SCALE: i8 = 20;
+const EXPONENTIAL_MIN_SCALE: i8 = -10;
+
+/// How measurements of an instrument are combined into a metric stream.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Aggregation {
+    /// The default aggregation for the instrument kind.
+    Default,
+    /// Discard all measurements.
+    Drop,
+    /// Arithmetic sum of all measurements.
+    Sum,
+    /// The most recent measurement.
+    LastValue,
+    /// Histogram with explicitly configured bucket boundaries.
+    ExplicitBucketHistogram {
+        /// Upper inclusive bounds of each bucket, strictly increasing.
+        boundaries: Vec<f64>,
+        /// Whether min and max are recorded.
+        record_min_max: bool,
+    },
+    /// Histogram whose buckets grow exponentially with base `2^(2^-scale)`.
+    Base2ExponentialHistogram {
+        /// Maximum number of buckets kept for positive values.
+        max_size: u32,
+        /// Starting (and highest) scale.
+        max_scale: i8,
+        /// Whether min and max are recorded.
+        record_min_max: bool,
+    },
+}
+
+impl Aggregation {
+    /// Explicit bucket histogram with the given boundaries.
+    pub fn explicit_histogram(boundaries: Vec<f64>) -> Aggregation {
+        Aggregation::ExplicitBucketHistogram {
+            boundaries,
+            record_min_max: true,
+        }
+    }
+
+    /// Exponential histogram with the default size (160) and scale (20).
+    pub fn exponential_histogram() -> Aggregation {
+        Aggregation::Base2ExponentialHistogram {
+            max_size: EXPONENTIAL_MAX_SIZE,
+            max_scale: EXPONENTIAL_MAX_SCALE,
+            record_min_max: true,
+        }
+    }
+
+    /// Replaces `Default` with the aggregation used for `kind`.
+    pub(crate) fn resolve(&self, kind: InstrumentKind) -> Aggregation {
+        match (self, kind) {
+            (Aggregation::Default, InstrumentKind::Counter) => Aggregation::Sum,
+            (aggregation, _) => aggregation.clone(),
+        }
+    }
+
+    pub(crate) fn validate(&self) -> MetricsResult<()> {
+        match self {
+            Aggregation::ExplicitBucketHistogram { boundaries, .. } => {
+                if boundaries.iter().any(|b| b.is_nan() || b.is_infinite()) {
+                    return Err(MetricsError::InvalidView(
+                        "histogram boundaries must be finite".into(),
+                    ));
+                }
+                if boundaries.windows(2).any(|w| w[0] >= w[1]) {
+                    return Err(MetricsError::InvalidView(
+                        "histogram boundaries must be strictly increasing".into(),
+                    ));
+                }
+                Ok(())
+            }
+            Aggregation::Base2ExponentialHistogram {
+                max_size,
+                max_scale,
+                ..
+            } => {
+                if *max_size < 2 {
+                    return Err(MetricsError::InvalidView(
+                        "exponential histogram max_size must be at least 2".into(),
+                    ));
+                }
+                if !(EXPONENTIAL_MIN_SCALE..=EXPONENTIAL_MAX_SCALE).contains(max_scale) {
+                    return Err(MetricsError::InvalidView(format!(
+                        "exponential histogram max_scale must be in [{}, {}]",
+                        EXPONENTIAL_MIN_SCALE, EXPONENTIAL_MAX_SCALE
+                    )));
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Running state of an explicit bucket histogram for one attribute set.
+#[derive(Clone, Debug)]
+pub(crate) struct HistogramBuckets {
+    pub(crate) counts: Vec<u64>,
+    pub(crate) count: u64,
+    pub(crate) sum: u64,
+    pub(crate) min: u64,
+    pub(crate) max: u64,
+}
+
+impl HistogramBuckets {
+    pub(crate) fn new(bucket_count: usize) -> HistogramBuckets {
+        HistogramBuckets {
+            counts: vec![0; bucket_count],
+            count: 0,
+            sum: 0,
+            min: u64::MAX,
+            max: 0,
+        }
+    }
+
+    /// Records `value`; bucket `i` holds values in `(boundaries[i-1], boundaries[i]]`.
+    pub(crate) fn record(&mut self, boundaries: &[f64], value: u64) {
+        let index = boundaries.partition_point(|bound| *bound < value as f64);
+        self.counts[index] += 1;
+        self.count += 1;
+        self.sum += value;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+}
+
+/// Running state of a base-2 exponential histogram for one attribute set.
+///
+/// Counters only record non-negative values, so only the zero bucket and the
+/// positive range are tracked.
+#[derive(Clone, Debug)]
+pub(crate) struct ExponentialBuckets {
+    pub(crate) max_size: usize,
+    pub(crate) scale: i8,
+    pub(crate) zero_count: u64,
+    pub(crate) offset: i32,
+    pub(crate) counts: Vec<u64>,
+    pub(crate) count: u64,
+    pub(crate) sum: u64,
+    pub(crate) min: u64,
+    pub(crate) max: u64,
+}
+
+impl ExponentialBuckets {
+    pub(crate) fn new(max_size: u32, max_scale: i8) -> ExponentialBuckets {
+        ExponentialBuckets {
+            max_size: max_size as usize,
+            scale: max_scale,
+            zero_count: 0,
+            offset: 0,
+            counts: Vec::new(),
+            count: 0,
+            sum: 0,
+            min: u64::MAX,
+            max: 0,
+        }
+    }
+
+    pub(crate) fn record(&mut self, value: u64) {
+        self.count += 1;
+        self.sum += value;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+
+        if value == 0 {
+            self.zero_count += 1;
+            return;
+        }
+
+        let mut index = bucket_index(value, self.scale);
+        if self.counts.is_empty() {
+            self.offset = index;
+            self.counts.push(1);
+            return;
+        }
+
+        let low = self.offset.min(index);
+        let high = (self.offset + self.counts.len() as i32 - 1).max(index);
+        let change = self.scale_change(low, high);
+        if change > 0 {
+            self.downscale(change);
+            index = bucket_index(value, self.scale);
+        }
+
+        if index < self.offset {
+            let grow = (self.offset - index) as usize;
+            let mut counts = vec![0; grow];
+            counts.append(&mut self.counts);
+            self.counts = counts;
+            self.offset = index;
+        } else if index >= self.offset + self.counts.len() as i32 {
+            self.counts.resize((index - self.offset) as usize + 1, 0);
+        }
+        self.counts[(index - self.offset) as usize] += 1;
+    }
+
+    /// How far the scale has to drop for `[low, high]` to fit in `max_size` buckets.
+    fn scale_change(&self, mut low: i32, mut high: i32) -> i8 {
+        let mut change = 0;
+        while high - low + 1 > self.max_size as i32 && self.scale - change > EXPONENTIAL_MIN_SCALE {
+            low >>= 1;
+            high >>= 1;
+            change += 1;
+        }
+        change
+    }
+
+    fn downscale(&mut self, change: i8) {
+        let mut counts: Vec<u64> = Vec::new();
+        let new_offset = self.offset >> change;
+        for (i, count) in self.counts.iter().enumerate() {
+            let index = ((self.offset + i as i32) >> change) - new_offset;
+            let index = index as usize;
+            if index >= counts.len() {
+                counts.resize(index + 1, 0);
+            }
+            counts[index] += count;
+        }
+        self.counts = counts;
+        self.offset = new_offset;
+        self.scale -= change;
+    }
+}
+
+/// Index of the bucket `(base^index, base^(index+1)]` holding `value`, where
+/// `base = 2^(2^-scale)`.
+pub(crate) fn bucket_index(value: u64, scale: i8) -> i32 {
+    let exponent = 63 - value.leading_zeros() as i32;
+    let is_power_of_two = value.is_power_of_two();
+    if scale <= 0 {
+        let exponent = if is_power_of_two {
+            exponent - 1
+        } else {
+            exponent
+        };
+        return exponent >> -scale;
+    }
+    if is_power_of_two {
+        return (exponent << scale) - 1;
+    }
+    let scale_factor = f64::from(1u32 << scale) / std::f64::consts::LN_2;
+    ((value as f64).ln() * scale_factor).ceil() as i32 - 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explicit_buckets_are_upper_inclusive() {
+        let boundaries = [5.0, 10.0];
+        let mut buckets = HistogramBuckets::new(boundaries.len() + 1);
+        for value in [0, 5, 6, 10, 11] {
+            buckets.record(&boundaries, value);
+        }
+        assert_eq!(buckets.counts, vec![2, 2, 1]);
+        assert_eq!(buckets.sum, 32);
+        assert_eq!((buckets.min, buckets.max), (0, 11));
+    }
+
+    #[test]
+    fn exponential_index_matches_scale() {
+        // scale 0: base 2, bucket i holds (2^i, 2^(i+1)]
+        assert_eq!(bucket_index(1, 0), -1);
+        assert_eq!(bucket_index(2, 0), 0);
+        assert_eq!(bucket_index(3, 0), 1);
+        assert_eq!(bucket_index(4, 0), 1);
+        // scale 1: base sqrt(2)
+        assert_eq!(bucket_index(2, 1), 1);
+        assert_eq!(bucket_index(3, 1), 3);
+        // scale -1: base 4
+        assert_eq!(bucket_index(4, -1), 0);
+        assert_eq!(bucket_index(5, -1), 1);
+    }
+
+    #[test]
+    fn exponential_downscales_to_fit() {
+        let mut buckets = ExponentialBuckets::new(4, 20);
+        for value in [0, 1, 2, 1024, 1 << 20] {
+            buckets.record(value);
+        }
+        assert!(buckets.counts.len() <= 4);
+        assert_eq!(buckets.zero_count, 1);
+        assert_eq!(buckets.counts.iter().sum::<u64>(), 4);
+        assert_eq!(buckets.count, 5);
+        for value in [1, 2, 1024, 1 << 20] {
+            let index = bucket_index(value, buckets.scale);
+            assert!(index >= buckets.offset);
+            assert!(index < buckets.offset + buckets.counts.len() as i32);
+        }
+    }
+}
@@ -13,7 +13,7 @@ impl MetricAttributes {
         let hash_value = calculate_hash(&attributes_vec);
         MetricAttributes {
             attributes: attributes_vec,
-            hash_value: hash_value,
+            hash_value,
         }
     }
 
@@ -21,7 +21,7 @@ impl MetricAttributes {
         let hash_value = calculate_hash(&attributes);
         MetricAttributes {
             attributes,
-            hash_value: hash_value,
+            hash_value,
         }
     }
 }
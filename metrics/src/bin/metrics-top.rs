@@ -0,0 +1,76 @@
+//! Live terminal dashboard for metrics.
+//!
+//! `metrics-top [--interval SECONDS] [URL]` scrapes the Prometheus endpoint
+//! at `URL`, such as `http://localhost:9464/metrics`. Without a URL it
+//! attaches to an in-process provider fed by a small demo workload.
+
+use metrics::{
+    aggregation::Aggregation, common::KeyValue, meter_provider::MeterProvider, metric::Temporality,
+    reader::ManualReader, top::Top, view::View,
+};
+use std::{env, process, thread, time::Duration};
+
+fn main() {
+    let mut interval = Duration::from_secs(1);
+    let mut url = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--interval" => {
+                let seconds = args.next().and_then(|seconds| seconds.parse::<f64>().ok());
+                match seconds.filter(|seconds| *seconds > 0.0) {
+                    Some(seconds) => interval = Duration::from_secs_f64(seconds),
+                    None => usage(),
+                }
+            }
+            "-h" | "--help" => usage(),
+            _ if url.is_none() && !arg.starts_with('-') => url = Some(arg),
+            _ => usage(),
+        }
+    }
+
+    let top = match url {
+        Some(url) => Top::scrape(&url).unwrap_or_else(|err| {
+            eprintln!("metrics-top: {}", err);
+            process::exit(2);
+        }),
+        None => Top::attach(demo()),
+    };
+    if let Err(err) = top.with_interval(interval).run() {
+        eprintln!("metrics-top: {}", err);
+        process::exit(1);
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("usage: metrics-top [--interval SECONDS] [URL]");
+    process::exit(2);
+}
+
+/// Records requests and latencies on a background thread.
+fn demo() -> ManualReader {
+    let reader = ManualReader::new(Temporality::Cumulative);
+    let provider = MeterProvider::builder()
+        .with_reader(reader.clone())
+        .with_view(
+            View::new("latency").with_aggregation(Aggregation::explicit_histogram(vec![
+                5.0, 10.0, 25.0, 50.0, 100.0, 250.0,
+            ])),
+        )
+        .build()
+        .unwrap();
+    thread::spawn(move || {
+        let meter = provider.get_meter("demo");
+        let requests = meter.create_counter("requests");
+        let latency = meter.create_counter("latency");
+        let routes = ["/", "/cart", "/checkout"];
+        for tick in 0u32.. {
+            let route = routes[tick as usize % routes.len()];
+            let attributes = [KeyValue::new("route", route)];
+            requests.add(1 + tick % 7, &attributes);
+            latency.add(3 + (tick * 37) % 200, &attributes);
+            thread::sleep(Duration::from_millis(20));
+        }
+    });
+    reader
+}
@@ -18,7 +18,7 @@ impl Key {
     /// # Examples
     ///
     /// ```
-    /// use opentelemetry::Key;
+    /// use metrics::common::Key;
     /// use std::sync::Arc;
     ///
     /// let key1 = Key::new("my_static_str");
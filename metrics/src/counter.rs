@@ -1,69 +1,282 @@
-use std::sync::Arc;
-use std::vec;
-use std::{
-    collections::HashMap,
-    sync::{RwLock},
-};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use std::{collections::HashMap, sync::RwLock};
 
+use crate::aggregation::Aggregation;
 use crate::attributes::MetricAttributes;
 use crate::common::KeyValue;
-use crate::metric::Metric;
-use crate::metricpoint::MetricPoint;
+use crate::error::MetricsError;
+use crate::metric::{
+    DataPoint, ExponentialHistogram, ExponentialHistogramDataPoint, Gauge, Histogram,
+    HistogramDataPoint, Metric, MetricData, Sum, Temporality,
+};
+use crate::metricpoint::{MetricPoint, PointValue};
+use crate::pipeline::StreamContext;
+use crate::view::Stream;
+
+const OVERFLOW_ATTRIBUTE: &str = "otel.metric.overflow";
 
+/// A monotonic counter.
+///
+/// A counter without streams, such as one handed out by a
+/// [`NoopMeterProvider`](crate::noop::NoopMeterProvider) or by a provider
+/// without readers, is a no-op: `add` returns after a single branch.
 #[derive(Clone)]
 pub struct Counter {
-    inner: Arc<CounterInner>,
+    inner: Option<Arc<CounterStreams>>,
+}
+
+struct CounterStreams {
+    streams: Vec<Arc<CounterInner>>,
+    is_shutdown: Arc<AtomicBool>,
 }
 
 impl Counter {
-    pub fn new(name: String) -> Counter {
+    pub(crate) fn new(streams: Vec<Arc<CounterInner>>, is_shutdown: Arc<AtomicBool>) -> Counter {
+        if streams.is_empty() {
+            return Counter::noop();
+        }
         Counter {
-            inner: Arc::new(CounterInner::new(name)),
+            inner: Some(Arc::new(CounterStreams {
+                streams,
+                is_shutdown,
+            })),
         }
     }
 
+    pub(crate) const fn noop() -> Counter {
+        Counter { inner: None }
+    }
+
+    /// Adds `value` to every stream of the counter. Does nothing once the
+    /// provider has been shut down.
+    #[inline]
     pub fn add(&self, value: u32, attributes: &[KeyValue]) {
-        self.inner.add(value, attributes);
+        if let Some(inner) = &self.inner {
+            inner.add(value, attributes);
+        }
     }
 
-    pub fn collect(&self) -> Metric {
-        self.inner.collect()
+    /// Collects the streams belonging to `pipeline`.
+    pub(crate) fn collect(&self, pipeline: usize) -> Vec<Metric> {
+        let Some(inner) = &self.inner else {
+            return vec![];
+        };
+        inner
+            .streams
+            .iter()
+            .filter(|stream| stream.pipeline == pipeline)
+            .map(|stream| stream.collect())
+            .collect()
     }
 }
 
+impl CounterStreams {
+    fn add(&self, value: u32, attributes: &[KeyValue]) {
+        if self.is_shutdown.load(Ordering::Relaxed) {
+            return;
+        }
+        for stream in self.streams.iter() {
+            stream.add(value, attributes);
+        }
+    }
+}
+
+/// Aggregation state of one metric stream produced by a counter.
+///
+/// Once the number of distinct attribute sets reaches the cardinality limit,
+/// new attribute sets are aggregated into a single point carrying the
+/// `otel.metric.overflow=true` attribute.
 pub struct CounterInner {
     metric_points_map: RwLock<HashMap<MetricAttributes, MetricPoint>>,
     zero_attribute_point: MetricPoint,
-    name: String,
+    overflow_point: MetricPoint,
+    /// Number of distinct attribute sets in `metric_points_map`.
+    point_count: AtomicUsize,
+    overflow_reported: AtomicBool,
+    stream: Stream,
+    pipeline: usize,
+    temporality: Temporality,
+    context: StreamContext,
+    start_time: Mutex<SystemTime>,
 }
 
 impl CounterInner {
-    pub fn new(name: String) -> CounterInner {
-        let counter = CounterInner {
+    pub(crate) fn new(
+        pipeline: usize,
+        stream: Stream,
+        temporality: Temporality,
+        context: StreamContext,
+    ) -> CounterInner {
+        CounterInner {
             metric_points_map: RwLock::new(HashMap::new()),
-            zero_attribute_point: MetricPoint::new(),
-            name: name,
-        };
-        counter
+            zero_attribute_point: MetricPoint::new(&stream.aggregation),
+            overflow_point: MetricPoint::new(&stream.aggregation),
+            point_count: AtomicUsize::new(0),
+            overflow_reported: AtomicBool::new(false),
+            start_time: Mutex::new(context.clock.now()),
+            stream,
+            pipeline,
+            temporality,
+            context,
+        }
     }
 
     pub fn collect(&self) -> Metric {
-        let mut metric_points: Vec<(Vec<KeyValue>, u32)> = Vec::new();
+        let mut points: Vec<(Vec<KeyValue>, PointValue)> = Vec::new();
+        let reset = self.temporality == Temporality::Delta;
 
-        for kv in self.metric_points_map.write().unwrap().drain() {
-            metric_points.push((kv.0.attributes.clone(), kv.1.get_sum()));
+        if let Some(value) = self.zero_attribute_point.collect(reset) {
+            points.push((vec![], value));
         }
 
-        metric_points.push((vec![], self.zero_attribute_point.get_sum()));
+        // Every attribute set is stored under both its incoming and its
+        // sorted order; report each point once, through the sorted key.
+        if reset {
+            let mut metric_points_map = self.metric_points_map.write().unwrap();
+            self.point_count.store(0, Ordering::Relaxed);
+            for (attributes, metric_point) in metric_points_map.drain() {
+                if !is_sorted(&attributes.attributes) {
+                    continue;
+                }
+                if let Some(value) = metric_point.collect(true) {
+                    points.push((attributes.attributes, value));
+                }
+            }
+        } else {
+            for (attributes, metric_point) in self.metric_points_map.read().unwrap().iter() {
+                if !is_sorted(&attributes.attributes) {
+                    continue;
+                }
+                if let Some(value) = metric_point.collect(false) {
+                    points.push((attributes.attributes.clone(), value));
+                }
+            }
+        }
 
-        let metric = Metric::new(self.name.clone(), metric_points);
+        if let Some(value) = self.overflow_point.collect(reset) {
+            points.push((vec![KeyValue::new(OVERFLOW_ATTRIBUTE, true)], value));
+        }
+
+        let time = self.context.clock.now();
+        let start_time = {
+            let mut start_time = self.start_time.lock().unwrap();
+            let previous = *start_time;
+            if reset {
+                *start_time = time;
+            }
+            previous
+        };
 
-        self.zero_attribute_point.reset();
+        Metric::new(
+            self.stream.name.clone(),
+            self.stream.description.clone(),
+            self.stream.unit.clone(),
+            self.metric_data(points, start_time, time),
+        )
+    }
 
-        metric
+    fn metric_data(
+        &self,
+        points: Vec<(Vec<KeyValue>, PointValue)>,
+        start_time: SystemTime,
+        time: SystemTime,
+    ) -> MetricData {
+        let temporality = self.temporality;
+        match &self.stream.aggregation {
+            Aggregation::LastValue => MetricData::Gauge(Gauge {
+                data_points: points
+                    .into_iter()
+                    .filter_map(|(attributes, value)| match value {
+                        PointValue::LastValue(value) => Some(DataPoint {
+                            attributes,
+                            start_time,
+                            time,
+                            value,
+                        }),
+                        _ => None,
+                    })
+                    .collect(),
+            }),
+            Aggregation::ExplicitBucketHistogram {
+                boundaries,
+                record_min_max,
+            } => MetricData::Histogram(Histogram {
+                data_points: points
+                    .into_iter()
+                    .filter_map(|(attributes, value)| match value {
+                        PointValue::Histogram(buckets) => Some(HistogramDataPoint {
+                            attributes,
+                            start_time,
+                            time,
+                            count: buckets.count,
+                            sum: buckets.sum,
+                            min: record_min_max.then_some(buckets.min),
+                            max: record_min_max.then_some(buckets.max),
+                            bounds: boundaries.clone(),
+                            bucket_counts: buckets.counts,
+                        }),
+                        _ => None,
+                    })
+                    .collect(),
+                temporality,
+            }),
+            Aggregation::Base2ExponentialHistogram { record_min_max, .. } => {
+                MetricData::ExponentialHistogram(ExponentialHistogram {
+                    data_points: points
+                        .into_iter()
+                        .filter_map(|(attributes, value)| match value {
+                            PointValue::ExponentialHistogram(buckets) => {
+                                Some(ExponentialHistogramDataPoint {
+                                    attributes,
+                                    start_time,
+                                    time,
+                                    count: buckets.count,
+                                    sum: buckets.sum,
+                                    min: record_min_max.then_some(buckets.min),
+                                    max: record_min_max.then_some(buckets.max),
+                                    scale: buckets.scale,
+                                    zero_count: buckets.zero_count,
+                                    positive_offset: buckets.offset,
+                                    positive_bucket_counts: buckets.counts,
+                                })
+                            }
+                            _ => None,
+                        })
+                        .collect(),
+                    temporality,
+                })
+            }
+            _ => MetricData::Sum(Sum {
+                data_points: points
+                    .into_iter()
+                    .filter_map(|(attributes, value)| match value {
+                        PointValue::Sum(value) => Some(DataPoint {
+                            attributes,
+                            start_time,
+                            time,
+                            value,
+                        }),
+                        _ => None,
+                    })
+                    .collect(),
+                temporality,
+                is_monotonic: true,
+            }),
+        }
     }
 
     pub fn add(&self, value: u32, attributes: &[KeyValue]) {
+        if self.stream.attribute_filter.is_all() {
+            self.add_filtered(value, attributes);
+        } else {
+            let attributes = self.stream.attribute_filter.apply(attributes);
+            self.add_filtered(value, &attributes);
+        }
+    }
+
+    fn add_filtered(&self, value: u32, attributes: &[KeyValue]) {
         if attributes.is_empty() {
             self.zero_attribute_point.add(value);
             return;
@@ -84,10 +297,19 @@ impl CounterInner {
 
             if let Some(metric_point) = metric_points_map.get(&metric_attributes_sorted) {
                 metric_point.add(value);
+            } else if self.point_count.load(Ordering::Relaxed) + 1 >= self.context.cardinality_limit
+            {
+                self.overflow_point.add(value);
+                if !self.overflow_reported.swap(true, Ordering::Relaxed) {
+                    (self.context.error_handler)(MetricsError::CardinalityLimitReached(
+                        self.stream.name.clone(),
+                    ));
+                }
             } else {
+                self.point_count.fetch_add(1, Ordering::Relaxed);
                 // insert both incoming order and sorted order
                 // insert in incoming order.
-                let mp_new = MetricPoint::new();
+                let mp_new = MetricPoint::new(&self.stream.aggregation);
                 mp_new.add(value);
                 metric_points_map.insert(metric_attributes, mp_new.clone());
 
@@ -98,10 +320,175 @@ impl CounterInner {
     }
 }
 
+fn is_sorted(attributes: &[KeyValue]) -> bool {
+    attributes.windows(2).all(|w| w[0].key <= w[1].key)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clock::SystemClock;
+    use crate::instrument::{Instrument, InstrumentKind};
+    use crate::view::AttributeFilter;
+
+    fn stream(aggregation: Aggregation) -> Stream {
+        let instrument = Instrument {
+            name: "requests".into(),
+            description: String::new(),
+            unit: String::new(),
+            kind: InstrumentKind::Counter,
+            meter_name: "meter".into(),
+        };
+        let mut stream = Stream::default_for(&instrument, &Aggregation::Default);
+        stream.aggregation = aggregation;
+        stream
+    }
+
+    fn context(cardinality_limit: usize) -> StreamContext {
+        StreamContext {
+            clock: Arc::new(SystemClock),
+            error_handler: Arc::new(|_| {}),
+            cardinality_limit,
+        }
+    }
+
+    fn delta(stream: Stream) -> CounterInner {
+        CounterInner::new(0, stream, Temporality::Delta, context(2000))
+    }
 
     #[test]
-    fn it_works() {}
+    fn sum_reports_each_attribute_set_once() {
+        let counter = delta(stream(Aggregation::Sum));
+        counter.add(1, &[KeyValue::new("b", "1"), KeyValue::new("a", "1")]);
+        counter.add(2, &[KeyValue::new("a", "1"), KeyValue::new("b", "1")]);
+        counter.add(4, &[]);
+
+        let MetricData::Sum(sum) = counter.collect().data else {
+            panic!("expected a sum");
+        };
+        let mut values: Vec<u64> = sum.data_points.iter().map(|p| p.value).collect();
+        values.sort();
+        assert_eq!(values, vec![3, 4]);
+
+        let MetricData::Sum(sum) = counter.collect().data else {
+            panic!("expected a sum");
+        };
+        assert!(sum.data_points.is_empty());
+    }
+
+    #[test]
+    fn cumulative_keeps_state_across_collections() {
+        let counter = CounterInner::new(
+            0,
+            stream(Aggregation::Sum),
+            Temporality::Cumulative,
+            context(2000),
+        );
+        counter.add(1, &[KeyValue::new("a", "1")]);
+        let first = counter.collect();
+        counter.add(2, &[KeyValue::new("a", "1")]);
+        let second = counter.collect();
+
+        let (MetricData::Sum(first), MetricData::Sum(second)) = (first.data, second.data) else {
+            panic!("expected sums");
+        };
+        assert_eq!(first.data_points[0].value, 1);
+        assert_eq!(second.data_points[0].value, 3);
+        assert_eq!(
+            first.data_points[0].start_time,
+            second.data_points[0].start_time
+        );
+        assert_eq!(second.temporality, Temporality::Cumulative);
+    }
+
+    #[test]
+    fn attribute_sets_beyond_limit_overflow() {
+        let counter =
+            CounterInner::new(0, stream(Aggregation::Sum), Temporality::Delta, context(3));
+        for user in ["a", "b", "c", "d"] {
+            // Incoming and sorted order count as one attribute set.
+            counter.add(
+                1,
+                &[KeyValue::new("user", user), KeyValue::new("route", "/")],
+            );
+            counter.add(
+                1,
+                &[KeyValue::new("route", "/"), KeyValue::new("user", user)],
+            );
+        }
+
+        let MetricData::Sum(sum) = counter.collect().data else {
+            panic!("expected a sum");
+        };
+        assert_eq!(sum.data_points.len(), 3);
+        let overflow = sum
+            .data_points
+            .iter()
+            .find(|p| p.attributes == vec![KeyValue::new(OVERFLOW_ATTRIBUTE, true)])
+            .unwrap();
+        assert_eq!(overflow.value, 4);
+
+        // Delta collection frees the slots again.
+        counter.add(1, &[KeyValue::new("user", "e")]);
+        let MetricData::Sum(sum) = counter.collect().data else {
+            panic!("expected a sum");
+        };
+        assert_eq!(
+            sum.data_points[0].attributes,
+            vec![KeyValue::new("user", "e")]
+        );
+    }
+
+    #[test]
+    fn filtered_attributes_collapse_into_one_point() {
+        let mut stream = stream(Aggregation::Sum);
+        stream.attribute_filter = AttributeFilter::Allow(["route".into()].into_iter().collect());
+        let counter = delta(stream);
+        counter.add(
+            1,
+            &[KeyValue::new("route", "/a"), KeyValue::new("user", "1")],
+        );
+        counter.add(
+            2,
+            &[KeyValue::new("user", "2"), KeyValue::new("route", "/a")],
+        );
+        counter.add(4, &[KeyValue::new("user", "3")]);
+
+        let MetricData::Sum(sum) = counter.collect().data else {
+            panic!("expected a sum");
+        };
+        let mut points: Vec<(Vec<KeyValue>, u64)> = sum
+            .data_points
+            .into_iter()
+            .map(|p| (p.attributes, p.value))
+            .collect();
+        points.sort_by_key(|(_, value)| *value);
+        assert_eq!(
+            points,
+            vec![(vec![KeyValue::new("route", "/a")], 3), (vec![], 4)]
+        );
+    }
+
+    #[test]
+    fn last_value_and_histogram() {
+        let gauge = delta(stream(Aggregation::LastValue));
+        gauge.add(5, &[]);
+        gauge.add(3, &[]);
+        let MetricData::Gauge(data) = gauge.collect().data else {
+            panic!("expected a gauge");
+        };
+        assert_eq!(data.data_points[0].value, 3);
+
+        let histogram = delta(stream(Aggregation::explicit_histogram(vec![1.0, 10.0])));
+        histogram.add(1, &[]);
+        histogram.add(7, &[]);
+        histogram.add(70, &[]);
+        let MetricData::Histogram(data) = histogram.collect().data else {
+            panic!("expected a histogram");
+        };
+        let point = &data.data_points[0];
+        assert_eq!(point.bucket_counts, vec![1, 1, 1]);
+        assert_eq!((point.count, point.sum), (3, 78));
+        assert_eq!((point.min, point.max), (Some(1), Some(70)));
+    }
 }
@@ -0,0 +1,337 @@
+//! Configuration read from the standard `OTEL_*` environment variables.
+//!
+//! The environment only fills in what the code leaves open: anything set on
+//! [`MeterProviderBuilder`](crate::meter_provider::MeterProviderBuilder) or on
+//! a reader wins. Invalid values are passed to the provider's error handler
+//! and otherwise ignored.
+//!
+//! | Variable | Effect |
+//! |---|---|
+//! | `OTEL_SDK_DISABLED` | `true` builds providers without readers |
+//! | `OTEL_METRICS_EXPORTER` | `otlp`, `console`, `prometheus` or `none`; used when no reader is configured |
+//! | `OTEL_METRIC_EXPORT_INTERVAL` | periodic reader interval, in milliseconds |
+//! | `OTEL_METRIC_EXPORT_TIMEOUT` | periodic reader export timeout, in milliseconds |
+//! | `OTEL_EXPORTER_OTLP_METRICS_TEMPORALITY_PREFERENCE` | `cumulative`, `delta` or `lowmemory` |
+//! | `OTEL_METRICS_CARDINALITY_LIMIT` | attribute sets per metric stream |
+//! | `OTEL_EXPORTER_PROMETHEUS_HOST` | address the `prometheus` exporter binds to, `localhost` by default |
+//! | `OTEL_EXPORTER_PROMETHEUS_PORT` | port the `prometheus` exporter listens on, 9464 by default |
+//! | `OTEL_EXPORTER_OTLP_ENDPOINT` | collector base URL for the OTLP exporters |
+//! | `OTEL_EXPORTER_OTLP_METRICS_ENDPOINT` | full metrics endpoint URL, used as given |
+//! | `OTEL_EXPORTER_OTLP_[METRICS_]HEADERS` | `name=value` pairs, comma-separated and percent-encoded |
+//! | `OTEL_EXPORTER_OTLP_[METRICS_]COMPRESSION` | `gzip` or `none` |
+//! | `OTEL_EXPORTER_OTLP_[METRICS_]TIMEOUT` | OTLP export timeout, in milliseconds |
+//! | `OTEL_EXPORTER_OTLP_[METRICS_]PROTOCOL` | `http/protobuf` (the default) or `grpc` for the `otlp` exporter |
+
+use std::{env, time::Duration};
+
+use crate::{
+    error::{MetricsError, MetricsResult},
+    exporter::{OtlpHttpExporter, StdoutExporter},
+    metric::Temporality,
+    periodic_reader::PeriodicReader,
+    prometheus_reader::PrometheusReader,
+    reader::MetricReader,
+};
+
+pub const OTEL_SDK_DISABLED: &str = "OTEL_SDK_DISABLED";
+pub const OTEL_METRICS_EXPORTER: &str = "OTEL_METRICS_EXPORTER";
+pub const OTEL_METRIC_EXPORT_INTERVAL: &str = "OTEL_METRIC_EXPORT_INTERVAL";
+pub const OTEL_METRIC_EXPORT_TIMEOUT: &str = "OTEL_METRIC_EXPORT_TIMEOUT";
+pub const OTEL_EXPORTER_OTLP_METRICS_TEMPORALITY_PREFERENCE: &str =
+    "OTEL_EXPORTER_OTLP_METRICS_TEMPORALITY_PREFERENCE";
+pub const OTEL_METRICS_CARDINALITY_LIMIT: &str = "OTEL_METRICS_CARDINALITY_LIMIT";
+pub const OTEL_EXPORTER_PROMETHEUS_HOST: &str = "OTEL_EXPORTER_PROMETHEUS_HOST";
+pub const OTEL_EXPORTER_PROMETHEUS_PORT: &str = "OTEL_EXPORTER_PROMETHEUS_PORT";
+pub const OTEL_EXPORTER_OTLP_ENDPOINT: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+pub const OTEL_EXPORTER_OTLP_METRICS_ENDPOINT: &str = "OTEL_EXPORTER_OTLP_METRICS_ENDPOINT";
+pub const OTEL_EXPORTER_OTLP_HEADERS: &str = "OTEL_EXPORTER_OTLP_HEADERS";
+pub const OTEL_EXPORTER_OTLP_METRICS_HEADERS: &str = "OTEL_EXPORTER_OTLP_METRICS_HEADERS";
+pub const OTEL_EXPORTER_OTLP_COMPRESSION: &str = "OTEL_EXPORTER_OTLP_COMPRESSION";
+pub const OTEL_EXPORTER_OTLP_METRICS_COMPRESSION: &str = "OTEL_EXPORTER_OTLP_METRICS_COMPRESSION";
+pub const OTEL_EXPORTER_OTLP_TIMEOUT: &str = "OTEL_EXPORTER_OTLP_TIMEOUT";
+pub const OTEL_EXPORTER_OTLP_METRICS_TIMEOUT: &str = "OTEL_EXPORTER_OTLP_METRICS_TIMEOUT";
+pub const OTEL_EXPORTER_OTLP_PROTOCOL: &str = "OTEL_EXPORTER_OTLP_PROTOCOL";
+pub const OTEL_EXPORTER_OTLP_METRICS_PROTOCOL: &str = "OTEL_EXPORTER_OTLP_METRICS_PROTOCOL";
+
+const DEFAULT_PROMETHEUS_HOST: &str = "localhost";
+const DEFAULT_PROMETHEUS_PORT: u16 = 9464;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum ExporterKind {
+    Otlp,
+    Console,
+    Prometheus,
+    None,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum OtlpProtocol {
+    HttpProtobuf,
+    Grpc,
+}
+
+/// Provider settings taken from the environment.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct EnvConfig {
+    pub(crate) disabled: bool,
+    pub(crate) exporters: Vec<ExporterKind>,
+    pub(crate) temporality: Option<Temporality>,
+    pub(crate) cardinality_limit: Option<usize>,
+    pub(crate) prometheus_host: Option<String>,
+    pub(crate) prometheus_port: Option<u16>,
+    pub(crate) otlp_protocol: Option<OtlpProtocol>,
+}
+
+impl EnvConfig {
+    /// Reads the process environment, returning the invalid values
+    /// alongside the settings that could be read.
+    pub(crate) fn from_env() -> (EnvConfig, Vec<MetricsError>) {
+        EnvConfig::from_lookup(|name| env::var(name).ok())
+    }
+
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> (EnvConfig, Vec<MetricsError>) {
+        let mut errors = vec![];
+        let config = EnvConfig {
+            disabled: report(&mut errors, read(&lookup, OTEL_SDK_DISABLED, parse_bool))
+                .unwrap_or(false),
+            exporters: report(
+                &mut errors,
+                read(&lookup, OTEL_METRICS_EXPORTER, parse_exporters),
+            )
+            .unwrap_or_default(),
+            temporality: report(
+                &mut errors,
+                read(
+                    &lookup,
+                    OTEL_EXPORTER_OTLP_METRICS_TEMPORALITY_PREFERENCE,
+                    parse_temporality,
+                ),
+            ),
+            cardinality_limit: report(
+                &mut errors,
+                read(&lookup, OTEL_METRICS_CARDINALITY_LIMIT, |value| {
+                    value.parse().ok().filter(|limit| *limit >= 2)
+                }),
+            ),
+            prometheus_host: report(
+                &mut errors,
+                read(&lookup, OTEL_EXPORTER_PROMETHEUS_HOST, |value| {
+                    Some(value.to_string())
+                }),
+            ),
+            prometheus_port: report(
+                &mut errors,
+                read(&lookup, OTEL_EXPORTER_PROMETHEUS_PORT, |value| {
+                    value.parse().ok()
+                }),
+            ),
+            otlp_protocol: report(
+                &mut errors,
+                read(&lookup, OTEL_EXPORTER_OTLP_METRICS_PROTOCOL, parse_protocol).and_then(
+                    |protocol| match protocol {
+                        Some(protocol) => Ok(Some(protocol)),
+                        None => read(&lookup, OTEL_EXPORTER_OTLP_PROTOCOL, parse_protocol),
+                    },
+                ),
+            ),
+        };
+        (config, errors)
+    }
+
+    /// Readers for the exporters named in `OTEL_METRICS_EXPORTER`. Readers
+    /// that cannot be created are left out and their errors added to
+    /// `errors`.
+    pub(crate) fn readers(&self, errors: &mut Vec<MetricsError>) -> Vec<Box<dyn MetricReader>> {
+        let mut readers: Vec<Box<dyn MetricReader>> = vec![];
+        for kind in &self.exporters {
+            match kind {
+                ExporterKind::Otlp => match self.otlp_reader() {
+                    Ok(reader) => readers.push(Box::new(reader)),
+                    Err(err) => errors.push(err),
+                },
+                ExporterKind::Console => {
+                    let exporter = self
+                        .temporality
+                        .map(StdoutExporter::new)
+                        .unwrap_or_default();
+                    readers.push(Box::new(PeriodicReader::new(exporter)));
+                }
+                ExporterKind::Prometheus => {
+                    let host = self
+                        .prometheus_host
+                        .as_deref()
+                        .unwrap_or(DEFAULT_PROMETHEUS_HOST);
+                    let port = self.prometheus_port.unwrap_or(DEFAULT_PROMETHEUS_PORT);
+                    match PrometheusReader::bind((host, port)) {
+                        Ok(reader) => readers.push(Box::new(reader)),
+                        Err(err) => errors.push(err),
+                    }
+                }
+                ExporterKind::None => {}
+            }
+        }
+        readers
+    }
+
+    fn otlp_reader(&self) -> MetricsResult<PeriodicReader> {
+        match self.otlp_protocol.unwrap_or(OtlpProtocol::HttpProtobuf) {
+            OtlpProtocol::HttpProtobuf => {
+                Ok(PeriodicReader::new(OtlpHttpExporter::builder().build()?))
+            }
+            #[cfg(feature = "grpc")]
+            OtlpProtocol::Grpc => Ok(PeriodicReader::new(
+                crate::exporter::OtlpGrpcExporter::builder().build()?,
+            )),
+            #[cfg(not(feature = "grpc"))]
+            OtlpProtocol::Grpc => Err(MetricsError::InvalidConfig(
+                "the grpc OTLP protocol needs the `grpc` feature".into(),
+            )),
+        }
+    }
+}
+
+pub(crate) fn export_interval() -> MetricsResult<Option<Duration>> {
+    read_env(OTEL_METRIC_EXPORT_INTERVAL, parse_millis)
+}
+
+pub(crate) fn export_timeout() -> MetricsResult<Option<Duration>> {
+    read_env(OTEL_METRIC_EXPORT_TIMEOUT, parse_millis)
+}
+
+/// Keeps a valid setting, moving an invalid one into `errors`.
+pub(crate) fn report<T>(
+    errors: &mut Vec<MetricsError>,
+    setting: MetricsResult<Option<T>>,
+) -> Option<T> {
+    setting.unwrap_or_else(|err| {
+        errors.push(err);
+        None
+    })
+}
+
+/// Reads `name` from the process environment; see [`read`].
+pub(crate) fn read_env<T>(
+    name: &str,
+    parse: impl Fn(&str) -> Option<T>,
+) -> MetricsResult<Option<T>> {
+    read(&|name| env::var(name).ok(), name, parse)
+}
+
+/// Reads `name`, treating an empty value as unset.
+fn read<T>(
+    lookup: &impl Fn(&str) -> Option<String>,
+    name: &str,
+    parse: impl Fn(&str) -> Option<T>,
+) -> MetricsResult<Option<T>> {
+    let Some(value) = lookup(name).filter(|value| !value.trim().is_empty()) else {
+        return Ok(None);
+    };
+    match parse(value.trim()) {
+        Some(parsed) => Ok(Some(parsed)),
+        None => Err(MetricsError::InvalidConfig(format!(
+            "ignoring {}={:?}",
+            name, value
+        ))),
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+pub(crate) fn parse_millis(value: &str) -> Option<Duration> {
+    value
+        .parse()
+        .ok()
+        .filter(|millis| *millis > 0)
+        .map(Duration::from_millis)
+}
+
+fn parse_exporters(value: &str) -> Option<Vec<ExporterKind>> {
+    value
+        .split(',')
+        .map(|name| match name.trim().to_ascii_lowercase().as_str() {
+            "otlp" => Some(ExporterKind::Otlp),
+            "console" => Some(ExporterKind::Console),
+            "prometheus" => Some(ExporterKind::Prometheus),
+            "none" => Some(ExporterKind::None),
+            _ => None,
+        })
+        .collect()
+}
+
+fn parse_protocol(value: &str) -> Option<OtlpProtocol> {
+    match value {
+        "http/protobuf" => Some(OtlpProtocol::HttpProtobuf),
+        "grpc" => Some(OtlpProtocol::Grpc),
+        _ => None,
+    }
+}
+
+pub(crate) fn parse_temporality(value: &str) -> Option<Temporality> {
+    match value.to_ascii_lowercase().as_str() {
+        "cumulative" => Some(Temporality::Cumulative),
+        // Counters are reported as deltas under the low-memory preference.
+        "delta" | "lowmemory" => Some(Temporality::Delta),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn config(vars: &[(&str, &str)]) -> (EnvConfig, Vec<MetricsError>) {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        EnvConfig::from_lookup(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn reads_valid_values() {
+        let (config, errors) = config(&[
+            (OTEL_SDK_DISABLED, "TRUE"),
+            (OTEL_METRICS_EXPORTER, "console, none"),
+            (
+                OTEL_EXPORTER_OTLP_METRICS_TEMPORALITY_PREFERENCE,
+                "lowmemory",
+            ),
+            (OTEL_METRICS_CARDINALITY_LIMIT, "100"),
+            (OTEL_EXPORTER_OTLP_PROTOCOL, "grpc"),
+        ]);
+        assert!(errors.is_empty());
+        assert_eq!(
+            config,
+            EnvConfig {
+                disabled: true,
+                exporters: vec![ExporterKind::Console, ExporterKind::None],
+                temporality: Some(Temporality::Delta),
+                cardinality_limit: Some(100),
+                otlp_protocol: Some(OtlpProtocol::Grpc),
+                ..Default::default()
+            }
+        );
+        assert_eq!(config.readers(&mut vec![]).len(), 1);
+    }
+
+    #[test]
+    fn reports_invalid_values() {
+        let (config, errors) = config(&[
+            (OTEL_SDK_DISABLED, "yes"),
+            (OTEL_METRICS_EXPORTER, "console,carrier-pigeon"),
+            (OTEL_METRICS_CARDINALITY_LIMIT, "1"),
+            (OTEL_EXPORTER_OTLP_METRICS_TEMPORALITY_PREFERENCE, ""),
+        ]);
+        assert_eq!(config, EnvConfig::default());
+        assert_eq!(errors.len(), 3);
+        assert_eq!(parse_millis("0"), None);
+        assert_eq!(parse_millis("1500"), Some(Duration::from_millis(1500)));
+    }
+}
@@ -0,0 +1,53 @@
+use std::{fmt, sync::Arc, time::Duration};
+
+/// Errors reported by the metrics SDK.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetricsError {
+    /// A view was configured in a way that cannot be applied.
+    InvalidView(String),
+    /// The reader is not registered with a live `MeterProvider`.
+    ReaderNotRegistered,
+    /// An exporter failed to deliver a collection.
+    Export(String),
+    /// An export did not finish within its timeout.
+    ExportTimeout(Duration),
+    /// The provider or reader has already been shut down.
+    AlreadyShutdown,
+    /// A metric stream reached its cardinality limit; further attribute sets
+    /// are aggregated into the overflow point.
+    CardinalityLimitReached(String),
+    /// The provider configuration is invalid.
+    InvalidConfig(String),
+}
+
+impl fmt::Display for MetricsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetricsError::InvalidView(msg) => write!(f, "invalid view: {}", msg),
+            MetricsError::ReaderNotRegistered => {
+                write!(f, "reader is not registered with a meter provider")
+            }
+            MetricsError::Export(msg) => write!(f, "export failed: {}", msg),
+            MetricsError::ExportTimeout(timeout) => {
+                write!(f, "export did not finish within {:?}", timeout)
+            }
+            MetricsError::AlreadyShutdown => write!(f, "already shut down"),
+            MetricsError::CardinalityLimitReached(stream) => {
+                write!(f, "cardinality limit reached for '{}'", stream)
+            }
+            MetricsError::InvalidConfig(msg) => write!(f, "invalid configuration: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for MetricsError {}
+
+pub type MetricsResult<T> = Result<T, MetricsError>;
+
+/// Receives errors that happen away from the caller, such as failed periodic
+/// exports.
+pub type ErrorHandler = Arc<dyn Fn(MetricsError) + Send + Sync>;
+
+pub(crate) fn default_error_handler() -> ErrorHandler {
+    Arc::new(|err| eprintln!("metrics: {}", err))
+}
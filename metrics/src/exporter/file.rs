@@ -0,0 +1,453 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::{Map, Value};
+
+use crate::{
+    error::{MetricsError, MetricsResult},
+    instrument::InstrumentKind,
+    metric::{ResourceMetrics, Temporality},
+};
+
+use super::{
+    rotating_file::RotatingFile,
+    series::{self, SeriesValue},
+    PushMetricExporter,
+};
+
+const DEFAULT_MAX_FILE_SIZE: u64 = 64 * 1024 * 1024;
+const DEFAULT_MAX_BACKUPS: usize = 5;
+
+/// Row format written by the [`FileExporter`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FileFormat {
+    /// Comma-separated values with a header row at the start of every file.
+    #[default]
+    Csv,
+    /// One JSON object per row, keyed by column name.
+    JsonLines,
+}
+
+/// Appends every data point of every collection as a row to a CSV or
+/// JSON-lines file, for loading into a dataframe.
+///
+/// Rows follow collection order and always have these columns, in order:
+///
+/// | Column              | Content                                              |
+/// |---------------------|------------------------------------------------------|
+/// | `timestamp`         | End of the point's interval, RFC 3339 in UTC         |
+/// | `meter`             | Name of the meter                                    |
+/// | `metric`            | Name of the metric                                   |
+/// | `unit`              | Unit of the metric                                   |
+/// | `attributes.<key>`  | One per [attribute column](FileExporterBuilder::with_attribute_columns) |
+/// | `attributes`        | The other attributes, as a JSON object               |
+/// | `value`             | Value of a sum or gauge point                        |
+/// | `count`, `sum`      | Count and sum of a histogram point                   |
+/// | `min`, `max`        | Minimum and maximum of a histogram point             |
+///
+/// Columns that do not apply to a point are empty in CSV and `null` in JSON
+/// lines. Files are rotated like those of the
+/// [`OtlpFileExporter`](super::OtlpFileExporter), by size and optionally by
+/// age; a collection is never split across files.
+///
+/// ```no_run
+/// use metrics::exporter::{FileExporter, FileFormat};
+/// use metrics::periodic_reader::PeriodicReader;
+/// use std::time::Duration;
+///
+/// let exporter = FileExporter::builder("metrics.csv")
+///     .with_format(FileFormat::Csv)
+///     .with_attribute_columns(&["http.route"])
+///     .with_max_age(Duration::from_secs(3600))
+///     .build()
+///     .unwrap();
+/// let reader = PeriodicReader::new(exporter);
+/// ```
+#[derive(Debug)]
+pub struct FileExporter {
+    format: FileFormat,
+    attribute_columns: Vec<String>,
+    temporality: Temporality,
+    file: Mutex<RotatingFile>,
+}
+
+/// Configures a [`FileExporter`].
+#[derive(Debug)]
+pub struct FileExporterBuilder {
+    path: PathBuf,
+    format: FileFormat,
+    attribute_columns: Vec<String>,
+    max_file_size: u64,
+    max_age: Option<Duration>,
+    max_backups: usize,
+    temporality: Temporality,
+}
+
+impl FileExporter {
+    /// Writes to `path`, appending if it already exists.
+    pub fn builder(path: impl AsRef<Path>) -> FileExporterBuilder {
+        FileExporterBuilder {
+            path: path.as_ref().to_path_buf(),
+            format: FileFormat::default(),
+            attribute_columns: vec![],
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            max_age: None,
+            max_backups: DEFAULT_MAX_BACKUPS,
+            temporality: Temporality::Cumulative,
+        }
+    }
+}
+
+impl FileExporterBuilder {
+    pub fn with_format(mut self, format: FileFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Attributes given a column of their own, named `attributes.<key>`,
+    /// instead of going into the `attributes` object.
+    pub fn with_attribute_columns(mut self, keys: &[&str]) -> Self {
+        self.attribute_columns = keys.iter().map(|key| key.to_string()).collect();
+        self
+    }
+
+    /// Size in bytes a file may reach before it is rotated, 64 MiB by
+    /// default.
+    pub fn with_max_file_size(mut self, bytes: u64) -> Self {
+        self.max_file_size = bytes;
+        self
+    }
+
+    /// Rotates a file once it has been open this long. Off by default.
+    pub fn with_max_age(mut self, age: Duration) -> Self {
+        self.max_age = Some(age);
+        self
+    }
+
+    /// Number of rotated files to keep, 5 by default. With 0 the file is
+    /// truncated instead of rotated.
+    pub fn with_max_backups(mut self, backups: usize) -> Self {
+        self.max_backups = backups;
+        self
+    }
+
+    /// Temporality requested for every instrument. Defaults to cumulative.
+    pub fn with_temporality(mut self, temporality: Temporality) -> Self {
+        self.temporality = temporality;
+        self
+    }
+
+    pub fn build(self) -> MetricsResult<FileExporter> {
+        let header = match self.format {
+            FileFormat::Csv => {
+                let columns = columns(&self.attribute_columns);
+                let mut header = columns
+                    .iter()
+                    .map(|column| csv_field(column))
+                    .collect::<Vec<_>>()
+                    .join(",");
+                header.push('\n');
+                header.into_bytes()
+            }
+            FileFormat::JsonLines => vec![],
+        };
+        let file = RotatingFile::open(
+            &self.path,
+            self.max_file_size,
+            self.max_age,
+            self.max_backups,
+            header,
+        )
+        .map_err(|err| {
+            MetricsError::InvalidConfig(format!("cannot open {}: {}", self.path.display(), err))
+        })?;
+        Ok(FileExporter {
+            format: self.format,
+            attribute_columns: self.attribute_columns,
+            temporality: self.temporality,
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl PushMetricExporter for FileExporter {
+    fn export(&self, metrics: &ResourceMetrics, _timeout: Duration) -> MetricsResult<()> {
+        let rows = self.encode(metrics);
+        if rows.is_empty() {
+            return Ok(());
+        }
+        let mut file = self.file.lock().unwrap();
+        file.write(rows.as_bytes())
+            .map_err(|err| MetricsError::Export(format!("{}: {}", file.path().display(), err)))
+    }
+
+    fn temporality(&self, _kind: InstrumentKind) -> Temporality {
+        self.temporality
+    }
+
+    fn shutdown(&self) -> MetricsResult<()> {
+        let file = self.file.lock().unwrap();
+        file.sync()
+            .map_err(|err| MetricsError::Export(format!("{}: {}", file.path().display(), err)))
+    }
+}
+
+impl FileExporter {
+    fn encode(&self, metrics: &ResourceMetrics) -> String {
+        let columns = columns(&self.attribute_columns);
+        let mut out = String::new();
+        for series in series::series(metrics) {
+            let mut attributes = series::attributes_json(series.attributes);
+            let mut row = vec![
+                Value::from(rfc3339(series.time)),
+                Value::from(series.meter),
+                Value::from(series.metric.name.as_str()),
+                Value::from(series.metric.unit.as_str()),
+            ];
+            for key in &self.attribute_columns {
+                row.push(attributes.remove(key).unwrap_or(Value::Null));
+            }
+            row.push(Value::Object(attributes));
+            match series.value {
+                SeriesValue::Number(value) => {
+                    row.push(Value::from(value));
+                    row.extend([Value::Null, Value::Null, Value::Null, Value::Null]);
+                }
+                SeriesValue::Histogram {
+                    count,
+                    sum,
+                    min,
+                    max,
+                } => {
+                    row.push(Value::Null);
+                    row.extend([count, sum].map(Value::from));
+                    row.extend([min, max].map(Value::from));
+                }
+            }
+            match self.format {
+                FileFormat::Csv => {
+                    let fields: Vec<_> = row
+                        .iter()
+                        .map(|cell| match cell {
+                            Value::Null => String::new(),
+                            Value::String(text) => csv_field(text),
+                            other => csv_field(&other.to_string()),
+                        })
+                        .collect();
+                    out.push_str(&fields.join(","));
+                }
+                FileFormat::JsonLines => {
+                    let object: Map<String, Value> = columns.iter().cloned().zip(row).collect();
+                    out.push_str(&Value::Object(object).to_string());
+                }
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+fn columns(attribute_columns: &[String]) -> Vec<String> {
+    let mut columns: Vec<String> = ["timestamp", "meter", "metric", "unit"]
+        .map(String::from)
+        .to_vec();
+    columns.extend(
+        attribute_columns
+            .iter()
+            .map(|key| format!("attributes.{}", key)),
+    );
+    columns.extend(["attributes", "value", "count", "sum", "min", "max"].map(String::from));
+    columns
+}
+
+/// Quotes `field` if it holds a separator, quote or line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// `time` in RFC 3339, in UTC with millisecond precision.
+fn rfc3339(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((seconds / 86_400) as i64);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        seconds % 86_400 / 3600,
+        seconds % 3600 / 60,
+        seconds % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// The Gregorian date `days` after 1970-01-01, after Howard Hinnant's
+/// `civil_from_days`.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        common::KeyValue,
+        metric::{DataPoint, Histogram, HistogramDataPoint, Metric, MetricData, ScopeMetrics, Sum},
+        resource::Resource,
+        scope::InstrumentationScope,
+    };
+    use std::fs;
+
+    fn metrics() -> ResourceMetrics {
+        let time = UNIX_EPOCH + Duration::from_millis(1_700_000_000_250);
+        ResourceMetrics {
+            resource: Resource::empty(),
+            scope_metrics: vec![ScopeMetrics {
+                scope: InstrumentationScope::new("http"),
+                metrics: vec![
+                    Metric::new(
+                        "requests".into(),
+                        String::new(),
+                        String::new(),
+                        MetricData::Sum(Sum {
+                            data_points: vec![DataPoint {
+                                attributes: vec![
+                                    KeyValue::new("route", "/a,b"),
+                                    KeyValue::new("code", 200),
+                                ],
+                                start_time: time,
+                                time,
+                                value: 3,
+                            }],
+                            temporality: Temporality::Cumulative,
+                            is_monotonic: true,
+                        }),
+                    ),
+                    Metric::new(
+                        "latency".into(),
+                        String::new(),
+                        "ms".into(),
+                        MetricData::Histogram(Histogram {
+                            data_points: vec![HistogramDataPoint {
+                                attributes: vec![],
+                                start_time: time,
+                                time,
+                                count: 5,
+                                sum: 130,
+                                min: Some(5),
+                                max: Some(60),
+                                bounds: vec![10.0],
+                                bucket_counts: vec![4, 1],
+                            }],
+                            temporality: Temporality::Cumulative,
+                        }),
+                    ),
+                ],
+            }],
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("metrics-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// The CSV schema is stable: a header row, then one row per data point.
+    #[test]
+    fn writes_csv_schema() {
+        let dir = temp_dir("file-csv");
+        let path = dir.join("metrics.csv");
+        let exporter = FileExporter::builder(&path)
+            .with_attribute_columns(&["route", "missing"])
+            .build()
+            .unwrap();
+        exporter.export(&metrics(), Duration::from_secs(1)).unwrap();
+        exporter.shutdown().unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "timestamp,meter,metric,unit,attributes.route,attributes.missing,attributes,value,count,sum,min,max\n\
+             2023-11-14T22:13:20.250Z,http,requests,,\"/a,b\",,\"{\"\"code\"\":200}\",3,,,,\n\
+             2023-11-14T22:13:20.250Z,http,latency,ms,,,{},,5,130,5,60\n"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn writes_json_lines() {
+        let dir = temp_dir("file-jsonl");
+        let path = dir.join("metrics.jsonl");
+        let exporter = FileExporter::builder(&path)
+            .with_format(FileFormat::JsonLines)
+            .build()
+            .unwrap();
+        exporter.export(&metrics(), Duration::from_secs(1)).unwrap();
+        let rows: Vec<Value> = fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            rows[0],
+            serde_json::json!({
+                "timestamp": "2023-11-14T22:13:20.250Z",
+                "meter": "http",
+                "metric": "requests",
+                "unit": "",
+                "attributes": { "route": "/a,b", "code": 200 },
+                "value": 3,
+                "count": null,
+                "sum": null,
+                "min": null,
+                "max": null,
+            })
+        );
+        assert_eq!(rows[1]["count"], 5);
+        assert_eq!(rows[1]["value"], Value::Null);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rotates_by_age() {
+        let dir = temp_dir("file-age");
+        let path = dir.join("metrics.csv");
+        let exporter = FileExporter::builder(&path)
+            .with_max_age(Duration::ZERO)
+            .build()
+            .unwrap();
+        exporter.export(&metrics(), Duration::from_secs(1)).unwrap();
+        exporter.export(&metrics(), Duration::from_secs(1)).unwrap();
+
+        // Each file starts with its own header.
+        let backup = exporter.file.lock().unwrap().backup(1);
+        for path in [&path, &backup] {
+            let contents = fs::read_to_string(path).unwrap();
+            assert!(contents.starts_with("timestamp,meter,"));
+            assert_eq!(contents.lines().count(), 3);
+        }
+        assert_eq!(rfc3339(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
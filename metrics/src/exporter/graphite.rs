@@ -0,0 +1,415 @@
+use std::{
+    io::{self, Write},
+    net::{TcpStream, ToSocketAddrs},
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    common::KeyValue,
+    error::{MetricsError, MetricsResult},
+    instrument::InstrumentKind,
+    metric::{MetricData, ResourceMetrics, Temporality},
+};
+
+use super::PushMetricExporter;
+
+/// A local Carbon daemon on its plaintext port.
+pub const DEFAULT_CARBON_ADDRESS: &str = "127.0.0.1:2003";
+
+/// How attributes are written into Graphite series names.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum TagStyle {
+    /// `requests.route._a`: each attribute appended as `key.value` path
+    /// segments, sorted by key.
+    #[default]
+    Path,
+    /// `requests;route=/a`: Graphite 1.1 tagged series.
+    Tagged,
+}
+
+/// Sends every collected series to Carbon in the plaintext protocol,
+/// `path value timestamp`, over a TCP connection kept open between exports.
+///
+/// Sums and gauges are written as one series; histograms as `.count`, `.sum`,
+/// `.min` and `.max` series. A connection the server has closed is reopened
+/// before writing. An export that fails before any data was sent is retried
+/// once on a new connection; one that fails part way is not, since the
+/// server would receive the first series twice.
+///
+/// ```no_run
+/// use metrics::exporter::{GraphiteExporter, TagStyle};
+/// use metrics::periodic_reader::PeriodicReader;
+///
+/// let exporter = GraphiteExporter::builder("carbon:2003")
+///     .with_prefix("checkout")
+///     .with_tag_style(TagStyle::Tagged)
+///     .build();
+/// let reader = PeriodicReader::new(exporter);
+/// ```
+#[derive(Debug)]
+pub struct GraphiteExporter {
+    addr: String,
+    prefix: String,
+    tag_style: TagStyle,
+    temporality: Temporality,
+    connection: Mutex<Option<TcpStream>>,
+}
+
+/// Configures a [`GraphiteExporter`].
+#[derive(Debug)]
+pub struct GraphiteExporterBuilder {
+    addr: String,
+    prefix: String,
+    tag_style: TagStyle,
+    temporality: Temporality,
+}
+
+impl GraphiteExporter {
+    /// Sends to the Carbon plaintext listener at `addr`, for example
+    /// [`DEFAULT_CARBON_ADDRESS`].
+    pub fn builder(addr: &str) -> GraphiteExporterBuilder {
+        GraphiteExporterBuilder {
+            addr: addr.to_string(),
+            prefix: String::new(),
+            tag_style: TagStyle::default(),
+            temporality: Temporality::Cumulative,
+        }
+    }
+}
+
+impl GraphiteExporterBuilder {
+    /// Path prepended to every series, without a trailing dot.
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.trim_end_matches('.').to_string();
+        self
+    }
+
+    pub fn with_tag_style(mut self, tag_style: TagStyle) -> Self {
+        self.tag_style = tag_style;
+        self
+    }
+
+    /// Temporality requested for every instrument. Defaults to cumulative.
+    pub fn with_temporality(mut self, temporality: Temporality) -> Self {
+        self.temporality = temporality;
+        self
+    }
+
+    /// The connection is opened by the first export.
+    pub fn build(self) -> GraphiteExporter {
+        GraphiteExporter {
+            addr: self.addr,
+            prefix: self.prefix,
+            tag_style: self.tag_style,
+            temporality: self.temporality,
+            connection: Mutex::new(None),
+        }
+    }
+}
+
+impl PushMetricExporter for GraphiteExporter {
+    fn export(&self, metrics: &ResourceMetrics, timeout: Duration) -> MetricsResult<()> {
+        let payload = self.encode(metrics);
+        if payload.is_empty() {
+            return Ok(());
+        }
+        let mut connection = self.connection.lock().unwrap();
+        if connection.as_ref().is_some_and(is_closed) {
+            *connection = None;
+        }
+        let mut written = 0;
+        let mut result = self.write(&mut connection, payload.as_bytes(), &mut written, timeout);
+        if result.is_err() && written == 0 {
+            // Nothing was sent; the old connection may have gone stale.
+            *connection = None;
+            result = self.write(&mut connection, payload.as_bytes(), &mut written, timeout);
+        }
+        result.map_err(|err| {
+            *connection = None;
+            MetricsError::Export(format!("graphite {}: {}", self.addr, err))
+        })
+    }
+
+    fn temporality(&self, _kind: InstrumentKind) -> Temporality {
+        self.temporality
+    }
+
+    fn shutdown(&self) -> MetricsResult<()> {
+        self.connection.lock().unwrap().take();
+        Ok(())
+    }
+}
+
+impl GraphiteExporter {
+    /// Sends `payload`, counting the bytes accepted by the socket in
+    /// `written`.
+    fn write(
+        &self,
+        connection: &mut Option<TcpStream>,
+        payload: &[u8],
+        written: &mut usize,
+        timeout: Duration,
+    ) -> io::Result<()> {
+        if connection.is_none() {
+            let addr = self.addr.to_socket_addrs()?.next().ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, "address did not resolve")
+            })?;
+            *connection = Some(TcpStream::connect_timeout(&addr, timeout)?);
+        }
+        let stream = connection.as_mut().unwrap();
+        stream.set_write_timeout(Some(timeout))?;
+        while *written < payload.len() {
+            match stream.write(&payload[*written..]) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => *written += n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        stream.flush()
+    }
+
+    fn encode(&self, metrics: &ResourceMetrics) -> String {
+        let mut payload = String::new();
+        let mut push = |name: &str, attributes: &[KeyValue], value: u64, time: SystemTime| {
+            let seconds = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+            payload.push_str(&format!(
+                "{} {} {}\n",
+                self.series(name, attributes),
+                value,
+                seconds.as_secs()
+            ));
+        };
+        for metric in metrics.metrics() {
+            let name = metric.name.as_str();
+            let mut push_summary = |point: Summary| {
+                let series = [
+                    ("count", Some(point.count)),
+                    ("sum", Some(point.sum)),
+                    ("min", point.min),
+                    ("max", point.max),
+                ];
+                for (suffix, value) in series {
+                    if let Some(value) = value {
+                        let name = format!("{}.{}", name, suffix);
+                        push(&name, point.attributes, value, point.time);
+                    }
+                }
+            };
+            match &metric.data {
+                MetricData::Sum(sum) => {
+                    for point in &sum.data_points {
+                        push(name, &point.attributes, point.value, point.time);
+                    }
+                }
+                MetricData::Gauge(gauge) => {
+                    for point in &gauge.data_points {
+                        push(name, &point.attributes, point.value, point.time);
+                    }
+                }
+                MetricData::Histogram(histogram) => {
+                    for point in &histogram.data_points {
+                        push_summary(Summary {
+                            attributes: &point.attributes,
+                            time: point.time,
+                            count: point.count,
+                            sum: point.sum,
+                            min: point.min,
+                            max: point.max,
+                        });
+                    }
+                }
+                MetricData::ExponentialHistogram(histogram) => {
+                    for point in &histogram.data_points {
+                        push_summary(Summary {
+                            attributes: &point.attributes,
+                            time: point.time,
+                            count: point.count,
+                            sum: point.sum,
+                            min: point.min,
+                            max: point.max,
+                        });
+                    }
+                }
+            }
+        }
+        payload
+    }
+
+    fn series(&self, name: &str, attributes: &[KeyValue]) -> String {
+        let mut series = self.prefix.clone();
+        for segment in name.split('.') {
+            if !series.is_empty() {
+                series.push('.');
+            }
+            series.push_str(&path_segment(segment));
+        }
+        let mut attributes: Vec<(&str, String)> = attributes
+            .iter()
+            .map(|kv| (kv.key.as_str(), kv.value.to_string()))
+            .collect();
+        attributes.sort();
+        for (key, value) in attributes {
+            match self.tag_style {
+                TagStyle::Path => {
+                    series.push('.');
+                    series.push_str(&path_segment(key));
+                    series.push('.');
+                    series.push_str(&path_segment(&value));
+                }
+                TagStyle::Tagged => {
+                    if value.is_empty() {
+                        continue;
+                    }
+                    series.push(';');
+                    series.push_str(&tag(key, &[';', '!', '^', '=', ' ']));
+                    series.push('=');
+                    series.push_str(tag(&value, &[';', ' ']).trim_start_matches('~'));
+                }
+            }
+        }
+        series
+    }
+}
+
+/// The parts of a histogram point written as series.
+struct Summary<'a> {
+    attributes: &'a [KeyValue],
+    time: SystemTime,
+    count: u64,
+    sum: u64,
+    min: Option<u64>,
+    max: Option<u64>,
+}
+
+/// Keeps characters Graphite handles in path segments, replacing the rest.
+fn path_segment(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+fn tag(value: &str, special: &[char]) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if special.contains(&c) || c.is_control() {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+/// Whether the server closed `stream`, checked without blocking.
+fn is_closed(stream: &TcpStream) -> bool {
+    if stream.set_nonblocking(true).is_err() {
+        return true;
+    }
+    let closed = match stream.peek(&mut [0]) {
+        Ok(0) => true,
+        Ok(_) => false,
+        Err(err) => err.kind() != io::ErrorKind::WouldBlock,
+    };
+    closed || stream.set_nonblocking(false).is_err()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metric::{DataPoint, Metric, ScopeMetrics, Sum};
+    use crate::{resource::Resource, scope::InstrumentationScope};
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::time::Instant;
+
+    fn metrics(value: u64) -> ResourceMetrics {
+        let time = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        ResourceMetrics {
+            resource: Resource::empty(),
+            scope_metrics: vec![ScopeMetrics {
+                scope: InstrumentationScope::new("http"),
+                metrics: vec![Metric::new(
+                    "http.requests".into(),
+                    String::new(),
+                    String::new(),
+                    MetricData::Sum(Sum {
+                        data_points: vec![DataPoint {
+                            attributes: vec![
+                                KeyValue::new("route", "/a b"),
+                                KeyValue::new("code", "200"),
+                            ],
+                            start_time: time,
+                            time,
+                            value,
+                        }],
+                        temporality: Temporality::Cumulative,
+                        is_monotonic: true,
+                    }),
+                )],
+            }],
+        }
+    }
+
+    #[test]
+    fn formats_series_names() {
+        let exporter = GraphiteExporter::builder(DEFAULT_CARBON_ADDRESS)
+            .with_prefix("app.")
+            .build();
+        assert_eq!(
+            exporter.encode(&metrics(3)),
+            "app.http.requests.code.200.route._a_b 3 1700000000\n"
+        );
+        let exporter = GraphiteExporter::builder(DEFAULT_CARBON_ADDRESS)
+            .with_tag_style(TagStyle::Tagged)
+            .build();
+        assert_eq!(
+            exporter.encode(&metrics(3)),
+            "http.requests;code=200;route=/a_b 3 1700000000\n"
+        );
+    }
+
+    #[test]
+    fn reconnects_after_the_server_closes() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (lines, received) = mpsc::channel();
+        std::thread::spawn(move || {
+            for _ in 0..2 {
+                // Read one line per connection, then hang up.
+                let (stream, _) = listener.accept().unwrap();
+                let mut line = String::new();
+                BufReader::new(stream).read_line(&mut line).unwrap();
+                lines.send(line).unwrap();
+            }
+        });
+        let exporter = GraphiteExporter::builder(&addr).build();
+        exporter
+            .export(&metrics(1), Duration::from_secs(5))
+            .unwrap();
+        assert!(received.recv().unwrap().contains(" 1 "));
+        // Export again only once the client has seen the server hang up.
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !exporter
+            .connection
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(is_closed)
+        {
+            assert!(Instant::now() < deadline, "server did not close");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        exporter
+            .export(&metrics(2), Duration::from_secs(5))
+            .unwrap();
+        assert!(received.recv().unwrap().contains(" 2 "));
+    }
+}
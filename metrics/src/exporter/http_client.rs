@@ -0,0 +1,267 @@
+//! A minimal HTTP/1.1 client for the push exporters and for scraping pull
+//! endpoints. Each request opens a new connection; TLS is not supported.
+
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::{Duration, Instant},
+};
+
+use flate2::{write::GzEncoder, Compression};
+
+use crate::error::{MetricsError, MetricsResult};
+
+/// POSTs `body` to `endpoint` with the given extra headers, giving up at
+/// `deadline`.
+pub(crate) fn post(
+    endpoint: &Endpoint,
+    headers: &[(&str, &str)],
+    body: &[u8],
+    deadline: Instant,
+) -> io::Result<Response> {
+    request("POST", endpoint, headers, body, deadline)
+}
+
+/// GETs `endpoint` with the given extra headers, giving up at `deadline`.
+pub(crate) fn get(
+    endpoint: &Endpoint,
+    headers: &[(&str, &str)],
+    deadline: Instant,
+) -> io::Result<Response> {
+    request("GET", endpoint, headers, &[], deadline)
+}
+
+fn request(
+    method: &str,
+    endpoint: &Endpoint,
+    headers: &[(&str, &str)],
+    body: &[u8],
+    deadline: Instant,
+) -> io::Result<Response> {
+    let remaining = || {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            Err(io::Error::new(io::ErrorKind::TimedOut, "request timed out"))
+        } else {
+            Ok(remaining)
+        }
+    };
+    let addr = (endpoint.host.as_str(), endpoint.port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "endpoint did not resolve"))?;
+    let mut stream = TcpStream::connect_timeout(&addr, remaining()?)?;
+    stream.set_write_timeout(Some(remaining()?))?;
+    stream.set_read_timeout(Some(remaining()?))?;
+
+    let mut head = format!(
+        "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\n\
+         User-Agent: metrics-mini/{}\r\nConnection: close\r\n",
+        method,
+        endpoint.path,
+        endpoint.authority(),
+        body.len(),
+        env!("CARGO_PKG_VERSION"),
+    );
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(body)?;
+    stream.flush()?;
+    read_response(stream)
+}
+
+pub(crate) struct Response {
+    pub(crate) status: u16,
+    /// `Retry-After`, when given in seconds.
+    pub(crate) retry_after: Option<Duration>,
+    pub(crate) body: Vec<u8>,
+}
+
+fn read_response(stream: TcpStream) -> io::Result<Response> {
+    let invalid = |what: &str| io::Error::new(io::ErrorKind::InvalidData, what.to_string());
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let status = line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| invalid("malformed status line"))?;
+
+    let mut content_length = None;
+    let mut chunked = false;
+    let mut retry_after = None;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let (name, value) = (name.trim().to_ascii_lowercase(), value.trim());
+        match name.as_str() {
+            "content-length" => content_length = value.parse::<usize>().ok(),
+            "transfer-encoding" => chunked = value.eq_ignore_ascii_case("chunked"),
+            "retry-after" => retry_after = value.parse().ok().map(Duration::from_secs),
+            _ => {}
+        }
+    }
+
+    let mut body = vec![];
+    if chunked {
+        loop {
+            line.clear();
+            reader.read_line(&mut line)?;
+            let size = line.split(';').next().unwrap_or_default().trim();
+            let size = usize::from_str_radix(size, 16).map_err(|_| invalid("malformed chunk"))?;
+            if size == 0 {
+                break;
+            }
+            let start = body.len();
+            body.resize(start + size, 0);
+            reader.read_exact(&mut body[start..])?;
+            line.clear();
+            reader.read_line(&mut line)?;
+        }
+    } else if let Some(length) = content_length {
+        body.resize(length, 0);
+        reader.read_exact(&mut body)?;
+    } else {
+        reader.read_to_end(&mut body)?;
+    }
+    Ok(Response {
+        status,
+        retry_after,
+        body,
+    })
+}
+
+pub(crate) fn gzip(body: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(body)?;
+    encoder.finish()
+}
+
+/// An `http://` URL split into its parts.
+#[derive(Debug)]
+pub(crate) struct Endpoint {
+    pub(crate) host: String,
+    pub(crate) port: u16,
+    pub(crate) path: String,
+}
+
+impl Endpoint {
+    pub(crate) fn parse(url: &str) -> MetricsResult<Endpoint> {
+        let invalid = || MetricsError::InvalidConfig(format!("invalid endpoint {:?}", url));
+        let rest = match url.split_once("://") {
+            Some(("http", rest)) => rest,
+            Some(("https", _)) => {
+                return Err(MetricsError::InvalidConfig(format!(
+                    "endpoint {:?} needs TLS, which is not supported",
+                    url
+                )))
+            }
+            _ => return Err(invalid()),
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(slash) => rest.split_at(slash),
+            None => (rest, "/"),
+        };
+        let parse_port = |port: &str| port.parse::<u16>().map_err(|_| invalid());
+        let (host, port) = match authority.strip_prefix('[') {
+            // [v6 address]:port
+            Some(bracketed) => {
+                let (host, after) = bracketed.split_once(']').ok_or_else(invalid)?;
+                match after.strip_prefix(':') {
+                    Some(port) => (host, parse_port(port)?),
+                    None if after.is_empty() => (host, 80),
+                    None => return Err(invalid()),
+                }
+            }
+            None => match authority.split_once(':') {
+                Some((host, port)) => (host, parse_port(port)?),
+                None => (authority, 80),
+            },
+        };
+        if host.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Endpoint {
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+
+    /// `host:port`, with IPv6 addresses in brackets as in the URL.
+    pub(crate) fn authority(&self) -> String {
+        if self.host.contains(':') {
+            format!("[{}]:{}", self.host, self.port)
+        } else {
+            format!("{}:{}", self.host, self.port)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn parses_endpoints() {
+        let endpoint = Endpoint::parse("http://collector:4318/v1/metrics").unwrap();
+        assert_eq!(
+            (
+                endpoint.host.as_str(),
+                endpoint.port,
+                endpoint.path.as_str()
+            ),
+            ("collector", 4318, "/v1/metrics")
+        );
+        let endpoint = Endpoint::parse("http://[::1]:9000").unwrap();
+        assert_eq!((endpoint.host.as_str(), endpoint.port), ("::1", 9000));
+        assert_eq!(endpoint.authority(), "[::1]:9000");
+        assert_eq!(Endpoint::parse("http://[::1]/").unwrap().port, 80);
+        assert!(Endpoint::parse("https://collector").is_err());
+        assert!(Endpoint::parse("collector:4318").is_err());
+    }
+
+    #[test]
+    fn sends_bracketed_host_for_ipv6() {
+        // Hosts without IPv6 have nothing to check.
+        let Ok(listener) = TcpListener::bind("[::1]:0") else {
+            return;
+        };
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut head = vec![];
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                head.push(line.trim_end().to_string());
+                line.clear();
+            }
+            reader
+                .get_mut()
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+            head
+        });
+
+        let endpoint = Endpoint::parse(&format!("http://[::1]:{}/v1/metrics", port)).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        assert_eq!(post(&endpoint, &[], b"", deadline).unwrap().status, 200);
+        let head = server.join().unwrap();
+        assert!(
+            head.contains(&format!("Host: [::1]:{}", port)),
+            "sent {:?}",
+            head
+        );
+    }
+}
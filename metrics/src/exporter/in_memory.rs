@@ -0,0 +1,181 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::{
+    common::KeyValue,
+    error::MetricsResult,
+    instrument::InstrumentKind,
+    metric::{HistogramDataPoint, Metric, MetricData, ResourceMetrics, Temporality},
+};
+
+use super::PushMetricExporter;
+
+/// Keeps every exported batch in memory, for asserting on instrumentation in
+/// tests.
+///
+/// Clones share the same storage, so keep one handle and give a clone to a
+/// `PeriodicReader`; `MeterProvider::force_flush` then exports on demand.
+/// Series are matched by name and by their exact set of attributes, compared
+/// as strings.
+///
+/// ```
+/// use metrics::common::KeyValue;
+/// use metrics::exporter::InMemoryExporter;
+/// use metrics::meter_provider::MeterProvider;
+/// use metrics::periodic_reader::PeriodicReader;
+///
+/// let exporter = InMemoryExporter::default();
+/// let provider = MeterProvider::builder()
+///     .with_reader(PeriodicReader::new(exporter.clone()))
+///     .build()
+///     .unwrap();
+/// let counter = provider.get_meter("http").create_counter("requests");
+/// counter.add(3, &[KeyValue::new("route", "/a")]);
+///
+/// provider.force_flush().unwrap();
+/// assert_eq!(exporter.sum("requests", &[("route", "/a")]), 3);
+/// ```
+#[derive(Clone, Debug)]
+pub struct InMemoryExporter {
+    batches: Arc<Mutex<Vec<ResourceMetrics>>>,
+    temporality: Temporality,
+}
+
+impl Default for InMemoryExporter {
+    fn default() -> Self {
+        InMemoryExporter::new(Temporality::Cumulative)
+    }
+}
+
+impl InMemoryExporter {
+    pub fn new(temporality: Temporality) -> InMemoryExporter {
+        InMemoryExporter {
+            batches: Arc::new(Mutex::new(vec![])),
+            temporality,
+        }
+    }
+
+    /// Every batch exported since creation or the last [`reset`](Self::reset).
+    pub fn batches(&self) -> Vec<ResourceMetrics> {
+        self.batches.lock().unwrap().clone()
+    }
+
+    /// The metric named `name` in the most recent batch that has it.
+    pub fn metric(&self, name: &str) -> Option<Metric> {
+        let batches = self.batches.lock().unwrap();
+        batches
+            .iter()
+            .rev()
+            .find_map(|batch| batch.metrics().find(|metric| metric.name == name))
+            .cloned()
+    }
+
+    /// Forgets all exported batches.
+    pub fn reset(&self) {
+        self.batches.lock().unwrap().clear();
+    }
+
+    /// Value of the sum or gauge series, or 0 if it was never exported.
+    ///
+    /// Cumulative sums and gauges report their latest value; delta sums are
+    /// added up over all batches.
+    pub fn sum(&self, name: &str, attributes: &[(&str, &str)]) -> u64 {
+        let batches = self.batches.lock().unwrap();
+        let mut total = 0;
+        for batch in batches.iter() {
+            for metric in batch.metrics().filter(|metric| metric.name == name) {
+                let (points, delta) = match &metric.data {
+                    MetricData::Sum(sum) => {
+                        (&sum.data_points, sum.temporality == Temporality::Delta)
+                    }
+                    MetricData::Gauge(gauge) => (&gauge.data_points, false),
+                    _ => continue,
+                };
+                for point in points {
+                    if matches(&point.attributes, attributes) {
+                        total = if delta {
+                            total + point.value
+                        } else {
+                            point.value
+                        };
+                    }
+                }
+            }
+        }
+        total
+    }
+
+    /// The latest histogram data point of the series.
+    pub fn histogram(&self, name: &str, attributes: &[(&str, &str)]) -> Option<HistogramDataPoint> {
+        let MetricData::Histogram(histogram) = self.metric(name)?.data else {
+            return None;
+        };
+        histogram
+            .data_points
+            .into_iter()
+            .find(|point| matches(&point.attributes, attributes))
+    }
+}
+
+impl PushMetricExporter for InMemoryExporter {
+    fn export(&self, metrics: &ResourceMetrics, _timeout: Duration) -> MetricsResult<()> {
+        self.batches.lock().unwrap().push(metrics.clone());
+        Ok(())
+    }
+
+    fn temporality(&self, _kind: InstrumentKind) -> Temporality {
+        self.temporality
+    }
+}
+
+fn matches(actual: &[KeyValue], expected: &[(&str, &str)]) -> bool {
+    actual.len() == expected.len()
+        && expected.iter().all(|(key, value)| {
+            actual
+                .iter()
+                .any(|kv| kv.key.as_str() == *key && kv.value.to_string() == *value)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        aggregation::Aggregation, meter_provider::MeterProvider, periodic_reader::PeriodicReader,
+        view::View,
+    };
+
+    #[test]
+    fn asserts_on_exported_series() {
+        let exporter = InMemoryExporter::new(Temporality::Delta);
+        let provider = MeterProvider::builder()
+            .with_reader(PeriodicReader::new(exporter.clone()))
+            .with_view(
+                View::new("latency").with_aggregation(Aggregation::explicit_histogram(vec![10.0])),
+            )
+            .build()
+            .unwrap();
+        let meter = provider.get_meter("http");
+        let requests = meter.create_counter("requests");
+        let route_a = [KeyValue::new("route", "/a")];
+        requests.add(1, &route_a);
+        requests.add(2, &route_a);
+        requests.add(5, &[]);
+        provider.force_flush().unwrap();
+        requests.add(4, &route_a);
+        meter.create_counter("latency").add(12, &[]);
+        provider.force_flush().unwrap();
+
+        assert_eq!(exporter.batches().len(), 2);
+        assert_eq!(exporter.sum("requests", &[("route", "/a")]), 7);
+        assert_eq!(exporter.sum("requests", &[]), 5);
+        assert_eq!(exporter.sum("requests", &[("route", "/b")]), 0);
+        let latency = exporter.histogram("latency", &[]).unwrap();
+        assert_eq!(latency.bucket_counts, vec![0, 1]);
+
+        exporter.reset();
+        assert!(exporter.metric("requests").is_none());
+    }
+}
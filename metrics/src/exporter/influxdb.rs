@@ -0,0 +1,456 @@
+use std::{
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    common::KeyValue,
+    error::{MetricsError, MetricsResult},
+    instrument::InstrumentKind,
+    metric::{MetricData, ResourceMetrics, Temporality},
+};
+
+use super::{
+    http_client::{self, Endpoint},
+    Packets, PushMetricExporter,
+};
+
+const DEFAULT_MAX_PACKET_SIZE: usize = 1432;
+
+/// Writes every collected data point to InfluxDB or Telegraf as line
+/// protocol.
+///
+/// The measurement is the metric name and the tags are the point's
+/// attributes. Sums and gauges have a single integer `value` field;
+/// histograms have `count`, `sum`, `min` and `max` fields plus one cumulative
+/// `le_<bound>` count per bucket. Timestamps are in nanoseconds.
+///
+/// Lines are POSTed to the HTTP write API, with `Authorization: Token` when a
+/// token is set, or sent over UDP packed into datagrams of at most the
+/// configured size.
+///
+/// ```no_run
+/// use metrics::exporter::InfluxExporter;
+/// use metrics::periodic_reader::PeriodicReader;
+///
+/// let exporter = InfluxExporter::http("http://localhost:8086/api/v2/write?org=ops&bucket=app")
+///     .with_token("secret")
+///     .build()
+///     .unwrap();
+/// let reader = PeriodicReader::new(exporter);
+/// ```
+#[derive(Debug)]
+pub struct InfluxExporter {
+    transport: Transport,
+    temporality: Temporality,
+}
+
+/// Configures an [`InfluxExporter`].
+#[derive(Debug)]
+pub struct InfluxExporterBuilder {
+    target: Target,
+    headers: Vec<(String, String)>,
+    max_packet_size: usize,
+    temporality: Temporality,
+}
+
+#[derive(Debug)]
+enum Target {
+    Http(String),
+    Udp(String),
+}
+
+#[derive(Debug)]
+enum Transport {
+    Http {
+        endpoint: Endpoint,
+        headers: Vec<(String, String)>,
+    },
+    Udp {
+        socket: UdpSocket,
+        addr: SocketAddr,
+        max_packet_size: usize,
+    },
+}
+
+impl InfluxExporter {
+    /// Writes through the HTTP API at `url`, for example
+    /// `http://localhost:8086/api/v2/write?org=ops&bucket=app` or Telegraf's
+    /// `http://localhost:8186/write`.
+    pub fn http(url: &str) -> InfluxExporterBuilder {
+        InfluxExporterBuilder::new(Target::Http(url.to_string()))
+    }
+
+    /// Sends datagrams to a UDP listener at `addr`.
+    pub fn udp(addr: &str) -> InfluxExporterBuilder {
+        InfluxExporterBuilder::new(Target::Udp(addr.to_string()))
+    }
+}
+
+impl InfluxExporterBuilder {
+    fn new(target: Target) -> InfluxExporterBuilder {
+        InfluxExporterBuilder {
+            target,
+            headers: vec![],
+            max_packet_size: DEFAULT_MAX_PACKET_SIZE,
+            temporality: Temporality::Cumulative,
+        }
+    }
+
+    /// Authenticates HTTP writes with an InfluxDB API token.
+    pub fn with_token(self, token: &str) -> Self {
+        self.with_header("Authorization", &format!("Token {}", token))
+    }
+
+    /// Adds a header sent with every HTTP write.
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Largest UDP datagram sent, 1432 bytes by default.
+    pub fn with_max_packet_size(mut self, bytes: usize) -> Self {
+        self.max_packet_size = bytes;
+        self
+    }
+
+    /// Temporality requested for every instrument. Defaults to cumulative.
+    pub fn with_temporality(mut self, temporality: Temporality) -> Self {
+        self.temporality = temporality;
+        self
+    }
+
+    pub fn build(self) -> MetricsResult<InfluxExporter> {
+        let transport = match self.target {
+            Target::Http(url) => Transport::Http {
+                endpoint: Endpoint::parse(&url)?,
+                headers: self.headers,
+            },
+            Target::Udp(addr) => {
+                let invalid =
+                    |err: String| MetricsError::InvalidConfig(format!("influxdb: {}", err));
+                let addr = addr
+                    .to_socket_addrs()
+                    .map_err(|err| invalid(err.to_string()))?
+                    .next()
+                    .ok_or_else(|| invalid(format!("{} did not resolve", addr)))?;
+                let local = match addr {
+                    SocketAddr::V4(_) => "0.0.0.0:0",
+                    SocketAddr::V6(_) => "[::]:0",
+                };
+                Transport::Udp {
+                    socket: UdpSocket::bind(local).map_err(|err| invalid(err.to_string()))?,
+                    addr,
+                    max_packet_size: self.max_packet_size,
+                }
+            }
+        };
+        Ok(InfluxExporter {
+            transport,
+            temporality: self.temporality,
+        })
+    }
+}
+
+impl PushMetricExporter for InfluxExporter {
+    fn export(&self, metrics: &ResourceMetrics, timeout: Duration) -> MetricsResult<()> {
+        let lines = encode(metrics);
+        if lines.is_empty() {
+            return Ok(());
+        }
+        let export_error = |err: std::io::Error| MetricsError::Export(format!("influxdb: {}", err));
+        match &self.transport {
+            Transport::Http { endpoint, headers } => {
+                let mut request_headers = vec![("Content-Type", "text/plain; charset=utf-8")];
+                request_headers.extend(headers.iter().map(|(n, v)| (n.as_str(), v.as_str())));
+                let body = lines.join("\n");
+                let response = http_client::post(
+                    endpoint,
+                    &request_headers,
+                    body.as_bytes(),
+                    Instant::now() + timeout,
+                )
+                .map_err(export_error)?;
+                if !(200..300).contains(&response.status) {
+                    return Err(MetricsError::Export(format!(
+                        "influxdb responded with status {}: {}",
+                        response.status,
+                        String::from_utf8_lossy(&response.body).trim()
+                    )));
+                }
+                Ok(())
+            }
+            Transport::Udp {
+                socket,
+                addr,
+                max_packet_size,
+            } => {
+                let mut packets = Packets::new(*max_packet_size);
+                for line in lines {
+                    packets.push(line);
+                }
+                for packet in packets.finish() {
+                    socket
+                        .send_to(packet.as_bytes(), addr)
+                        .map_err(export_error)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn temporality(&self, _kind: InstrumentKind) -> Temporality {
+        self.temporality
+    }
+}
+
+/// One line per data point.
+fn encode(metrics: &ResourceMetrics) -> Vec<String> {
+    let mut lines = vec![];
+    for metric in metrics.metrics() {
+        let measurement = escape(&metric.name, &[',', ' ']);
+        let mut push = |attributes: &[KeyValue], fields: String, time| {
+            lines.push(format!(
+                "{}{} {} {}",
+                measurement,
+                tags(attributes),
+                fields,
+                super::otlp::proto::unix_nanos(time)
+            ));
+        };
+        match &metric.data {
+            MetricData::Sum(sum) => {
+                for point in &sum.data_points {
+                    push(
+                        &point.attributes,
+                        int_field("value", point.value),
+                        point.time,
+                    );
+                }
+            }
+            MetricData::Gauge(gauge) => {
+                for point in &gauge.data_points {
+                    push(
+                        &point.attributes,
+                        int_field("value", point.value),
+                        point.time,
+                    );
+                }
+            }
+            MetricData::Histogram(histogram) => {
+                for point in &histogram.data_points {
+                    let mut fields = summary_fields(point.count, point.sum, point.min, point.max);
+                    let mut cumulative = 0;
+                    for (index, count) in point.bucket_counts.iter().enumerate() {
+                        cumulative += count;
+                        let bound = point.bounds.get(index).copied().unwrap_or(f64::INFINITY);
+                        fields.push(',');
+                        fields.push_str(&int_field(&bucket_key(bound), cumulative));
+                    }
+                    push(&point.attributes, fields, point.time);
+                }
+            }
+            MetricData::ExponentialHistogram(histogram) => {
+                for point in &histogram.data_points {
+                    let mut fields = summary_fields(point.count, point.sum, point.min, point.max);
+                    fields.push(',');
+                    fields.push_str(&int_field("le_0", point.zero_count));
+                    let mut cumulative = point.zero_count;
+                    let base_exponent = (-(point.scale as f64)).exp2();
+                    for (index, count) in point.positive_bucket_counts.iter().enumerate() {
+                        cumulative += count;
+                        let bucket = point.positive_offset as f64 + index as f64;
+                        let bound = ((bucket + 1.0) * base_exponent).exp2();
+                        fields.push(',');
+                        fields.push_str(&int_field(&bucket_key(bound), cumulative));
+                    }
+                    push(&point.attributes, fields, point.time);
+                }
+            }
+        }
+    }
+    lines
+}
+
+/// `,key=value` pairs sorted by key, as InfluxDB prefers.
+fn tags(attributes: &[KeyValue]) -> String {
+    let mut tags: Vec<(String, String)> = attributes
+        .iter()
+        .map(|kv| {
+            (
+                escape_tag(kv.key.as_str()),
+                escape_tag(&kv.value.to_string()),
+            )
+        })
+        .filter(|(_, value)| !value.is_empty())
+        .collect();
+    tags.sort();
+    tags.iter()
+        .map(|(key, value)| format!(",{}={}", key, value))
+        .collect()
+}
+
+fn summary_fields(count: u64, sum: u64, min: Option<u64>, max: Option<u64>) -> String {
+    let mut fields = format!("{},sum={}", int_field("count", count), sum as f64);
+    if let Some(min) = min {
+        fields.push_str(&format!(",min={}", min as f64));
+    }
+    if let Some(max) = max {
+        fields.push_str(&format!(",max={}", max as f64));
+    }
+    fields
+}
+
+fn int_field(key: &str, value: u64) -> String {
+    format!("{}={}i", escape_tag(key), value.min(i64::MAX as u64))
+}
+
+fn bucket_key(bound: f64) -> String {
+    if bound.is_infinite() {
+        "le_inf".to_string()
+    } else {
+        format!("le_{}", bound)
+    }
+}
+
+/// Escapes tag keys, tag values and field keys.
+fn escape_tag(value: &str) -> String {
+    escape(value, &[',', '=', ' '])
+}
+
+fn escape(value: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        // Line protocol has no way to carry a newline.
+        let c = if c == '\n' { ' ' } else { c };
+        if special.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metric::{DataPoint, Histogram, HistogramDataPoint, Metric, ScopeMetrics, Sum};
+    use crate::{resource::Resource, scope::InstrumentationScope};
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::time::UNIX_EPOCH;
+
+    fn metrics() -> ResourceMetrics {
+        let time = UNIX_EPOCH + Duration::from_secs(1);
+        ResourceMetrics {
+            resource: Resource::empty(),
+            scope_metrics: vec![ScopeMetrics {
+                scope: InstrumentationScope::new("http"),
+                metrics: vec![
+                    Metric::new(
+                        "http requests,total".into(),
+                        String::new(),
+                        String::new(),
+                        MetricData::Sum(Sum {
+                            data_points: vec![DataPoint {
+                                attributes: vec![
+                                    KeyValue::new("route", "/a b"),
+                                    KeyValue::new("code", "a=b,c"),
+                                ],
+                                start_time: time,
+                                time,
+                                value: 3,
+                            }],
+                            temporality: Temporality::Cumulative,
+                            is_monotonic: true,
+                        }),
+                    ),
+                    Metric::new(
+                        "latency".into(),
+                        String::new(),
+                        "ms".into(),
+                        MetricData::Histogram(Histogram {
+                            data_points: vec![HistogramDataPoint {
+                                attributes: vec![],
+                                start_time: time,
+                                time,
+                                count: 5,
+                                sum: 130,
+                                min: Some(5),
+                                max: Some(60),
+                                bounds: vec![10.0, 50.0],
+                                bucket_counts: vec![4, 0, 1],
+                            }],
+                            temporality: Temporality::Cumulative,
+                        }),
+                    ),
+                ],
+            }],
+        }
+    }
+
+    #[test]
+    fn encodes_and_escapes_lines() {
+        assert_eq!(
+            encode(&metrics()),
+            [
+                r"http\ requests\,total,code=a\=b\,c,route=/a\ b value=3i 1000000000",
+                "latency count=5i,sum=130,min=5,max=60,le_10=4i,le_50=4i,le_inf=5i 1000000000",
+            ]
+        );
+    }
+
+    #[test]
+    fn writes_over_http_and_udp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!(
+            "http://{}/api/v2/write?bucket=app",
+            listener.local_addr().unwrap()
+        );
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut head = String::new();
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    length = value.trim().parse().unwrap();
+                }
+                if line.trim().is_empty() {
+                    break;
+                }
+                head.push_str(&line);
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            write!(reader.get_mut(), "HTTP/1.1 204 No Content\r\n\r\n").unwrap();
+            (head, String::from_utf8(body).unwrap())
+        });
+        let exporter = InfluxExporter::http(&url)
+            .with_token("secret")
+            .build()
+            .unwrap();
+        exporter.export(&metrics(), Duration::from_secs(5)).unwrap();
+        let (head, body) = server.join().unwrap();
+        assert!(head.starts_with("POST /api/v2/write?bucket=app HTTP/1.1"));
+        assert!(head.contains("Authorization: Token secret"));
+        assert_eq!(body, encode(&metrics()).join("\n"));
+
+        let listener = UdpSocket::bind("127.0.0.1:0").unwrap();
+        listener
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let exporter = InfluxExporter::udp(&listener.local_addr().unwrap().to_string())
+            .with_max_packet_size(80)
+            .build()
+            .unwrap();
+        exporter.export(&metrics(), Duration::from_secs(5)).unwrap();
+        let mut buf = [0; 1500];
+        for expected in encode(&metrics()) {
+            let len = listener.recv(&mut buf).unwrap();
+            assert_eq!(std::str::from_utf8(&buf[..len]).unwrap(), expected);
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use std::time::Duration;
+
+use crate::{
+    error::MetricsResult,
+    instrument::InstrumentKind,
+    metric::{ResourceMetrics, Temporality},
+};
+
+mod file;
+mod graphite;
+pub(crate) mod http_client;
+mod in_memory;
+mod influxdb;
+pub mod otlp;
+pub mod prometheus;
+mod rotating_file;
+mod series;
+mod statsd;
+mod stdout;
+
+pub use file::{FileExporter, FileExporterBuilder, FileFormat};
+pub use graphite::{GraphiteExporter, GraphiteExporterBuilder, TagStyle, DEFAULT_CARBON_ADDRESS};
+pub use in_memory::InMemoryExporter;
+pub use influxdb::{InfluxExporter, InfluxExporterBuilder};
+#[cfg(feature = "grpc")]
+pub use otlp::OtlpGrpcExporter;
+pub use otlp::{Compression, OtlpFileExporter, OtlpHttpExporter};
+pub use statsd::{HistogramType, StatsdExporter, StatsdExporterBuilder, DEFAULT_STATSD_ADDRESS};
+pub use stdout::{ConsoleFormat, StdoutExporter};
+
+/// Receives the metrics a `PeriodicReader` collects and ships them elsewhere.
+pub trait PushMetricExporter: Send + Sync + 'static {
+    /// Exports one collection. Implementations should give up once `timeout`
+    /// has elapsed.
+    fn export(&self, metrics: &ResourceMetrics, timeout: Duration) -> MetricsResult<()>;
+
+    /// Temporality the exporter wants for instruments of `kind`.
+    fn temporality(&self, _kind: InstrumentKind) -> Temporality {
+        Temporality::Cumulative
+    }
+
+    /// Releases any resources held by the exporter. Called once, after the
+    /// final export.
+    fn shutdown(&self) -> MetricsResult<()> {
+        Ok(())
+    }
+}
+
+/// Newline-separated lines grouped into datagrams of a bounded size.
+pub(crate) struct Packets {
+    max_size: usize,
+    packets: Vec<String>,
+    current: String,
+}
+
+impl Packets {
+    pub(crate) fn new(max_size: usize) -> Packets {
+        Packets {
+            max_size,
+            packets: vec![],
+            current: String::new(),
+        }
+    }
+
+    pub(crate) fn push(&mut self, line: String) {
+        if line.is_empty() {
+            return;
+        }
+        if !self.current.is_empty() && self.current.len() + 1 + line.len() > self.max_size {
+            self.packets.push(std::mem::take(&mut self.current));
+        }
+        if !self.current.is_empty() {
+            self.current.push('\n');
+        }
+        self.current.push_str(&line);
+    }
+
+    pub(crate) fn finish(mut self) -> Vec<String> {
+        if !self.current.is_empty() {
+            self.packets.push(self.current);
+        }
+        self.packets
+    }
+}
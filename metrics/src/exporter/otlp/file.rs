@@ -0,0 +1,163 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::Duration,
+};
+
+use crate::{
+    error::{MetricsError, MetricsResult},
+    exporter::{rotating_file::RotatingFile, PushMetricExporter},
+    instrument::InstrumentKind,
+    metric::{ResourceMetrics, Temporality},
+};
+
+use super::json;
+
+const DEFAULT_MAX_FILE_SIZE: u64 = 64 * 1024 * 1024;
+const DEFAULT_MAX_BACKUPS: usize = 5;
+
+/// Appends every collection to a file as one line of OTLP-JSON, the format
+/// the collector's `otlpjsonfile` receiver replays.
+///
+/// Before a line would take the file past its size limit, the file is
+/// rotated: `metrics.jsonl` becomes `metrics.jsonl.1`, older backups move up
+/// by one and the oldest beyond the backup limit is deleted.
+///
+/// ```no_run
+/// use metrics::exporter::OtlpFileExporter;
+/// use metrics::periodic_reader::PeriodicReader;
+///
+/// let exporter = OtlpFileExporter::builder("metrics.jsonl")
+///     .with_max_file_size(16 * 1024 * 1024)
+///     .with_max_backups(3)
+///     .build()
+///     .unwrap();
+/// let reader = PeriodicReader::new(exporter);
+/// ```
+#[derive(Debug)]
+pub struct OtlpFileExporter {
+    temporality: Temporality,
+    file: Mutex<RotatingFile>,
+}
+
+/// Configures an [`OtlpFileExporter`].
+#[derive(Debug)]
+pub struct OtlpFileExporterBuilder {
+    path: PathBuf,
+    max_file_size: u64,
+    max_backups: usize,
+    temporality: Temporality,
+}
+
+impl OtlpFileExporter {
+    /// Writes to `path`, appending if it already exists.
+    pub fn builder(path: impl AsRef<Path>) -> OtlpFileExporterBuilder {
+        OtlpFileExporterBuilder {
+            path: path.as_ref().to_path_buf(),
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            max_backups: DEFAULT_MAX_BACKUPS,
+            temporality: Temporality::Cumulative,
+        }
+    }
+}
+
+impl OtlpFileExporterBuilder {
+    /// Size in bytes a file may reach before it is rotated, 64 MiB by
+    /// default. A single line larger than this still gets a file of its own.
+    pub fn with_max_file_size(mut self, bytes: u64) -> Self {
+        self.max_file_size = bytes;
+        self
+    }
+
+    /// Number of rotated files to keep, 5 by default. With 0 the file is
+    /// truncated instead of rotated.
+    pub fn with_max_backups(mut self, backups: usize) -> Self {
+        self.max_backups = backups;
+        self
+    }
+
+    /// Temporality requested for every instrument. Defaults to cumulative.
+    pub fn with_temporality(mut self, temporality: Temporality) -> Self {
+        self.temporality = temporality;
+        self
+    }
+
+    pub fn build(self) -> MetricsResult<OtlpFileExporter> {
+        let file = RotatingFile::open(
+            &self.path,
+            self.max_file_size,
+            None,
+            self.max_backups,
+            vec![],
+        )
+        .map_err(|err| {
+            MetricsError::InvalidConfig(format!("cannot open {}: {}", self.path.display(), err))
+        })?;
+        Ok(OtlpFileExporter {
+            temporality: self.temporality,
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl PushMetricExporter for OtlpFileExporter {
+    fn export(&self, metrics: &ResourceMetrics, _timeout: Duration) -> MetricsResult<()> {
+        let mut line = json::encode_request(metrics).to_string();
+        line.push('\n');
+        let mut file = self.file.lock().unwrap();
+        file.write(line.as_bytes())
+            .map_err(|err| MetricsError::Export(format!("{}: {}", file.path().display(), err)))
+    }
+
+    fn temporality(&self, _kind: InstrumentKind) -> Temporality {
+        self.temporality
+    }
+
+    fn shutdown(&self) -> MetricsResult<()> {
+        let file = self.file.lock().unwrap();
+        file.sync()
+            .map_err(|err| MetricsError::Export(format!("{}: {}", file.path().display(), err)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::Resource;
+    use std::fs;
+
+    #[test]
+    fn rotates_by_size() {
+        let dir = std::env::temp_dir().join(format!("metrics-otlp-file-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("metrics.jsonl");
+        let metrics = ResourceMetrics {
+            resource: Resource::empty(),
+            scope_metrics: vec![],
+        };
+        let line_len = json::encode_request(&metrics).to_string().len() as u64 + 1;
+
+        // Two lines fit in a file; five exports leave 1 + 2 + 2 lines.
+        let exporter = OtlpFileExporter::builder(&path)
+            .with_max_file_size(line_len * 2)
+            .with_max_backups(2)
+            .build()
+            .unwrap();
+        for _ in 0..5 {
+            exporter.export(&metrics, Duration::from_secs(1)).unwrap();
+        }
+        exporter.shutdown().unwrap();
+
+        let file = exporter.file.lock().unwrap();
+        let lines = |path: &Path| fs::read_to_string(path).unwrap().lines().count();
+        assert_eq!(lines(&path), 1);
+        assert_eq!(lines(&file.backup(1)), 2);
+        assert_eq!(lines(&file.backup(2)), 2);
+        assert!(!file.backup(3).exists());
+        for line in fs::read_to_string(&path).unwrap().lines() {
+            let request: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert!(request["resourceMetrics"].is_array());
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
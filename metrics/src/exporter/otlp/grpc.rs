@@ -0,0 +1,596 @@
+use std::{
+    future::Future,
+    panic, thread,
+    time::{Duration, Instant},
+};
+
+use std::io::Read;
+
+use bytes::Bytes;
+use flate2::read::GzDecoder;
+use http::{
+    header::{HeaderName, HeaderValue},
+    HeaderMap, Method, Request, StatusCode,
+};
+use tokio::{
+    net::TcpStream,
+    runtime::{Handle, Runtime},
+};
+
+use crate::{
+    error::{MetricsError, MetricsResult},
+    exporter::{
+        http_client::{gzip, Endpoint},
+        PushMetricExporter,
+    },
+    instrument::InstrumentKind,
+    metric::{ResourceMetrics, Temporality},
+    resource::percent_decode,
+};
+
+use super::{backoff, proto, Compression, OtlpConfig};
+
+const DEFAULT_ENDPOINT: &str = "http://localhost:4317";
+const EXPORT_PATH: &str = "/opentelemetry.proto.collector.metrics.v1.MetricsService/Export";
+
+const STATUS_OK: u32 = 0;
+const STATUS_UNKNOWN: u32 = 2;
+const STATUS_UNAVAILABLE: u32 = 14;
+
+/// Pushes collections to an OTLP/gRPC endpoint.
+///
+/// Each export opens a plaintext HTTP/2 connection and calls
+/// `MetricsService/Export` once. Calls failing with a retryable `grpc-status`
+/// (`CANCELLED`, `DEADLINE_EXCEEDED`, `RESOURCE_EXHAUSTED`, `ABORTED`,
+/// `OUT_OF_RANGE`, `UNAVAILABLE` or `DATA_LOSS`) are retried with exponential
+/// backoff for as long as the export timeout allows.
+///
+/// The exporter runs its calls on a private runtime, so it can be used with
+/// or without tokio, including from inside async code.
+///
+/// ```no_run
+/// use metrics::exporter::OtlpGrpcExporter;
+/// use metrics::periodic_reader::PeriodicReader;
+///
+/// let exporter = OtlpGrpcExporter::builder()
+///     .with_endpoint("http://collector:4317")
+///     .with_metadata("x-tenant", "checkout")
+///     .build()
+///     .unwrap();
+/// let reader = PeriodicReader::new(exporter);
+/// ```
+#[derive(Debug)]
+pub struct OtlpGrpcExporter {
+    endpoint: Endpoint,
+    config: OtlpConfig,
+    metadata: HeaderMap,
+    runtime: ExportRuntime,
+}
+
+/// Configures an [`OtlpGrpcExporter`]. Unset settings come from the
+/// `OTEL_EXPORTER_OTLP_*` environment variables.
+#[derive(Debug, Default)]
+pub struct OtlpGrpcExporterBuilder {
+    endpoint: Option<String>,
+    config: OtlpConfig,
+}
+
+impl OtlpGrpcExporter {
+    pub fn builder() -> OtlpGrpcExporterBuilder {
+        OtlpGrpcExporterBuilder::default()
+    }
+}
+
+impl OtlpGrpcExporterBuilder {
+    /// URL of the collector, `http://localhost:4317` by default. Any path is
+    /// ignored.
+    pub fn with_endpoint(mut self, endpoint: &str) -> Self {
+        self.endpoint = Some(endpoint.to_string());
+        self
+    }
+
+    /// Adds a metadata entry sent with every call.
+    pub fn with_metadata(mut self, name: &str, value: &str) -> Self {
+        self.config
+            .headers
+            .push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.config.compression = Some(compression);
+        self
+    }
+
+    /// Temporality requested for every instrument. Defaults to cumulative.
+    pub fn with_temporality(mut self, temporality: Temporality) -> Self {
+        self.config.temporality = Some(temporality);
+        self
+    }
+
+    /// Upper bound for a single export, including retries. The reader's
+    /// export timeout applies when it is shorter.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeout = Some(timeout);
+        self
+    }
+
+    pub fn build(self) -> MetricsResult<OtlpGrpcExporter> {
+        let env = super::env_settings(None)?;
+        let endpoint = self
+            .endpoint
+            .or(env.endpoint)
+            .unwrap_or_else(|| DEFAULT_ENDPOINT.to_string());
+        let config = self.config.or(env.config);
+        let mut metadata = HeaderMap::new();
+        for (name, value) in &config.headers {
+            let invalid = || MetricsError::InvalidConfig(format!("invalid metadata {:?}", name));
+            metadata.append(
+                HeaderName::from_bytes(name.as_bytes()).map_err(|_| invalid())?,
+                HeaderValue::from_str(value).map_err(|_| invalid())?,
+            );
+        }
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .enable_time()
+            .build()
+            .map_err(|err| MetricsError::InvalidConfig(err.to_string()))?;
+        Ok(OtlpGrpcExporter {
+            endpoint: Endpoint::parse(&endpoint)?,
+            config,
+            metadata,
+            runtime: ExportRuntime(Some(runtime)),
+        })
+    }
+}
+
+impl PushMetricExporter for OtlpGrpcExporter {
+    fn export(&self, metrics: &ResourceMetrics, timeout: Duration) -> MetricsResult<()> {
+        let message = proto::encode_request(metrics);
+        let (compressed, message) = match self.config.compression() {
+            Compression::Gzip => (true, gzip(&message).map_err(export_error)?),
+            Compression::None => (false, message),
+        };
+        // Length-prefixed message: compressed flag, then big-endian length.
+        let mut frame = Vec::with_capacity(message.len() + 5);
+        frame.push(compressed as u8);
+        frame.extend_from_slice(&(message.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&message);
+        let frame = Bytes::from(frame);
+
+        let deadline = Instant::now() + self.config.timeout(timeout);
+        let mut attempt = 0;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let reply = self
+                .runtime
+                .block_on(async {
+                    tokio::time::timeout(remaining, self.call(frame.clone(), remaining)).await
+                })
+                .map_err(|_| MetricsError::Export("export timed out".into()))??;
+            if reply.status == STATUS_OK {
+                return match reply
+                    .response()?
+                    .as_deref()
+                    .and_then(proto::decode_partial_success)
+                {
+                    Some((rejected, message)) => Err(MetricsError::Export(format!(
+                        "collector rejected {} data points: {}",
+                        rejected, message
+                    ))),
+                    None => Ok(()),
+                };
+            }
+            let delay = backoff(attempt, None);
+            if !is_retryable(reply.status) || Instant::now() + delay >= deadline {
+                return Err(MetricsError::Export(format!(
+                    "collector returned grpc-status {}: {}",
+                    reply.status, reply.status_message
+                )));
+            }
+            thread::sleep(delay);
+            attempt += 1;
+        }
+    }
+
+    fn temporality(&self, _kind: InstrumentKind) -> Temporality {
+        self.config.temporality()
+    }
+}
+
+/// A current-thread runtime that is safe to use, and drop, from inside
+/// another runtime.
+#[derive(Debug)]
+struct ExportRuntime(Option<Runtime>);
+
+impl ExportRuntime {
+    /// Runs `future` to completion. Blocking a thread that is driving another
+    /// runtime panics, so inside async code the future runs on a helper
+    /// thread while the caller waits.
+    fn block_on<F>(&self, future: F) -> F::Output
+    where
+        F: Future + Send,
+        F::Output: Send,
+    {
+        let runtime = self.0.as_ref().expect("runtime is only taken on drop");
+        if Handle::try_current().is_err() {
+            return runtime.block_on(future);
+        }
+        thread::scope(|scope| {
+            scope
+                .spawn(|| runtime.block_on(future))
+                .join()
+                .unwrap_or_else(|payload| panic::resume_unwind(payload))
+        })
+    }
+}
+
+impl Drop for ExportRuntime {
+    fn drop(&mut self) {
+        // Dropping a runtime blocks, which panics inside async code.
+        if let Some(runtime) = self.0.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+struct Reply {
+    status: u32,
+    status_message: String,
+    /// The response's `grpc-encoding`.
+    encoding: Option<String>,
+    /// The response as received, length prefix included.
+    message: Vec<u8>,
+}
+
+impl Reply {
+    /// The response message without its length prefix, gunzipped if the
+    /// collector compressed it, or `None` when there is none.
+    fn response(&self) -> MetricsResult<Option<Vec<u8>>> {
+        let Some(payload) = self.message.get(5..) else {
+            return Ok(None);
+        };
+        match (self.message[0], self.encoding.as_deref()) {
+            (0, _) => Ok(Some(payload.to_vec())),
+            (1, Some("gzip")) => {
+                let mut decoded = vec![];
+                GzDecoder::new(payload)
+                    .read_to_end(&mut decoded)
+                    .map_err(export_error)?;
+                Ok(Some(decoded))
+            }
+            (1, encoding) => Err(MetricsError::Export(format!(
+                "collector sent a compressed response with unsupported grpc-encoding {:?}",
+                encoding.unwrap_or("")
+            ))),
+            (flag, _) => Err(MetricsError::Export(format!(
+                "collector sent a response with invalid compressed flag {}",
+                flag
+            ))),
+        }
+    }
+}
+
+impl OtlpGrpcExporter {
+    async fn call(&self, frame: Bytes, timeout: Duration) -> MetricsResult<Reply> {
+        let host = self.endpoint.host.as_str();
+        let stream = TcpStream::connect((host, self.endpoint.port))
+            .await
+            .map_err(export_error)?;
+        let (client, connection) = h2::client::handshake(stream).await.map_err(h2_error)?;
+        let connection = tokio::spawn(connection);
+        let reply = self.send(client, frame, timeout).await;
+        // Awaiting the aborted task drops the connection, closing the socket.
+        connection.abort();
+        let _ = connection.await;
+        reply
+    }
+
+    async fn send(
+        &self,
+        client: h2::client::SendRequest<Bytes>,
+        frame: Bytes,
+        timeout: Duration,
+    ) -> MetricsResult<Reply> {
+        let mut request = Request::builder()
+            .method(Method::POST)
+            .uri(format!(
+                "http://{}{}",
+                self.endpoint.authority(),
+                EXPORT_PATH
+            ))
+            .header("content-type", "application/grpc")
+            .header("te", "trailers")
+            .header("grpc-timeout", format!("{}m", timeout.as_millis().max(1)))
+            .header(
+                "user-agent",
+                concat!("metrics-mini/", env!("CARGO_PKG_VERSION")),
+            )
+            .body(())
+            .map_err(|err| MetricsError::Export(err.to_string()))?;
+        if self.config.compression() == Compression::Gzip {
+            let headers = request.headers_mut();
+            headers.insert("grpc-encoding", HeaderValue::from_static("gzip"));
+            headers.insert("grpc-accept-encoding", HeaderValue::from_static("gzip"));
+        }
+        request.headers_mut().extend(self.metadata.clone());
+
+        let mut client = client.ready().await.map_err(h2_error)?;
+        let (response, mut body) = client.send_request(request, false).map_err(h2_error)?;
+        body.send_data(frame, true).map_err(h2_error)?;
+        let (head, mut body) = response.await.map_err(h2_error)?.into_parts();
+
+        // A trailers-only response carries the status in its headers.
+        if let Some(status) = grpc_status(&head.headers) {
+            return Ok(Reply {
+                status,
+                status_message: grpc_message(&head.headers),
+                encoding: None,
+                message: vec![],
+            });
+        }
+        if head.status != StatusCode::OK {
+            let status = match head.status.as_u16() {
+                429 | 502 | 503 | 504 => STATUS_UNAVAILABLE,
+                _ => STATUS_UNKNOWN,
+            };
+            return Ok(Reply {
+                status,
+                status_message: format!("HTTP status {}", head.status),
+                encoding: None,
+                message: vec![],
+            });
+        }
+        let encoding = head
+            .headers
+            .get("grpc-encoding")
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let mut message = vec![];
+        while let Some(chunk) = body.data().await {
+            let chunk = chunk.map_err(h2_error)?;
+            let _ = body.flow_control().release_capacity(chunk.len());
+            message.extend_from_slice(&chunk);
+        }
+        let trailers = body.trailers().await.map_err(h2_error)?.unwrap_or_default();
+        Ok(Reply {
+            status: grpc_status(&trailers).unwrap_or(STATUS_UNKNOWN),
+            status_message: grpc_message(&trailers),
+            encoding,
+            message,
+        })
+    }
+}
+
+fn grpc_status(headers: &HeaderMap) -> Option<u32> {
+    headers.get("grpc-status")?.to_str().ok()?.parse().ok()
+}
+
+fn grpc_message(headers: &HeaderMap) -> String {
+    let message = headers
+        .get("grpc-message")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    percent_decode(message).unwrap_or_else(|| message.to_string())
+}
+
+/// Status codes the OTLP specification allows retrying.
+fn is_retryable(status: u32) -> bool {
+    matches!(status, 1 | 4 | 8 | 10 | 11 | 14 | 15)
+}
+
+fn export_error(err: std::io::Error) -> MetricsError {
+    MetricsError::Export(err.to_string())
+}
+
+fn h2_error(err: h2::Error) -> MetricsError {
+    MetricsError::Export(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    struct Received {
+        path: String,
+        headers: HeaderMap,
+        body: Vec<u8>,
+    }
+
+    #[derive(Clone, Copy, PartialEq)]
+    enum ReplyEncoding {
+        Identity,
+        Gzip,
+        /// Compressed without a `grpc-encoding` header.
+        Undeclared,
+    }
+
+    /// Answers each call with the next `(grpc-status, response message)`,
+    /// reporting every request it receives.
+    fn mock_collector(
+        replies: Vec<(u32, Vec<u8>)>,
+        encoding: ReplyEncoding,
+    ) -> (String, mpsc::Receiver<Received>) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        listener.set_nonblocking(true).unwrap();
+        let (requests, received) = mpsc::channel();
+        thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_io()
+                .build()
+                .unwrap();
+            runtime.block_on(async move {
+                let listener = tokio::net::TcpListener::from_std(listener).unwrap();
+                for (status, message) in replies {
+                    let (stream, _) = listener.accept().await.unwrap();
+                    let mut connection = h2::server::handshake(stream).await.unwrap();
+                    let (request, mut respond) = connection.accept().await.unwrap().unwrap();
+                    let (head, mut body) = request.into_parts();
+                    let mut data = vec![];
+                    while let Some(chunk) = body.data().await {
+                        data.extend_from_slice(&chunk.unwrap());
+                    }
+                    requests
+                        .send(Received {
+                            path: head.uri.path().to_string(),
+                            headers: head.headers,
+                            body: data,
+                        })
+                        .unwrap();
+
+                    let mut response = http::Response::builder()
+                        .header("content-type", "application/grpc")
+                        .body(())
+                        .unwrap();
+                    let message = match encoding {
+                        ReplyEncoding::Identity => message,
+                        _ => gzip(&message).unwrap(),
+                    };
+                    if encoding == ReplyEncoding::Gzip {
+                        response
+                            .headers_mut()
+                            .insert("grpc-encoding", HeaderValue::from_static("gzip"));
+                    }
+                    let mut send = respond.send_response(response, false).unwrap();
+                    let mut frame = vec![(encoding != ReplyEncoding::Identity) as u8];
+                    frame.extend_from_slice(&(message.len() as u32).to_be_bytes());
+                    frame.extend_from_slice(&message);
+                    send.send_data(Bytes::from(frame), false).unwrap();
+                    let mut trailers = HeaderMap::new();
+                    trailers.insert("grpc-status", status.into());
+                    trailers.insert("grpc-message", HeaderValue::from_static("try%20again"));
+                    send.send_trailers(trailers).unwrap();
+                    // Serve until the client hangs up.
+                    while connection.accept().await.is_some() {}
+                }
+            });
+        });
+        (endpoint, received)
+    }
+
+    fn empty_metrics() -> ResourceMetrics {
+        ResourceMetrics {
+            resource: crate::resource::Resource::new(vec![crate::common::KeyValue::new(
+                "service.name",
+                "checkout",
+            )]),
+            scope_metrics: vec![],
+        }
+    }
+
+    #[test]
+    fn retries_unavailable_with_metadata_and_gzip() {
+        let (endpoint, received) = mock_collector(
+            vec![(STATUS_UNAVAILABLE, vec![]), (STATUS_OK, vec![])],
+            ReplyEncoding::Identity,
+        );
+        let exporter = OtlpGrpcExporter::builder()
+            .with_endpoint(&endpoint)
+            .with_metadata("X-Tenant", "checkout")
+            .with_compression(Compression::Gzip)
+            .build()
+            .unwrap();
+        exporter
+            .export(&empty_metrics(), Duration::from_secs(5))
+            .unwrap();
+
+        let first = received.recv().unwrap();
+        let retried = received.recv().unwrap();
+        assert_eq!(first.body, retried.body);
+        assert_eq!(retried.path, EXPORT_PATH);
+        assert_eq!(retried.headers["content-type"], "application/grpc");
+        assert_eq!(retried.headers["grpc-encoding"], "gzip");
+        assert_eq!(retried.headers["x-tenant"], "checkout");
+        assert_eq!(retried.body[0], 1);
+        let length = u32::from_be_bytes(retried.body[1..5].try_into().unwrap()) as usize;
+        assert_eq!(length, retried.body.len() - 5);
+        let mut decoded = vec![];
+        GzDecoder::new(&retried.body[5..])
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, proto::encode_request(&empty_metrics()));
+    }
+
+    #[test]
+    fn reports_partial_success_and_does_not_retry_invalid_argument() {
+        // partial_success { rejected_data_points: 2, error_message: "bad" }
+        let partial = vec![0x0a, 7, 0x08, 2, 0x12, 3, b'b', b'a', b'd'];
+        let (endpoint, received) = mock_collector(
+            vec![(STATUS_OK, partial), (3, vec![])],
+            ReplyEncoding::Identity,
+        );
+        let exporter = OtlpGrpcExporter::builder()
+            .with_endpoint(&endpoint)
+            .build()
+            .unwrap();
+
+        let result = exporter.export(&empty_metrics(), Duration::from_secs(5));
+        assert_eq!(
+            result.err(),
+            Some(MetricsError::Export(
+                "collector rejected 2 data points: bad".into()
+            ))
+        );
+        let result = exporter.export(&empty_metrics(), Duration::from_secs(5));
+        assert_eq!(
+            result.err(),
+            Some(MetricsError::Export(
+                "collector returned grpc-status 3: try again".into()
+            ))
+        );
+        assert_eq!(received.iter().count(), 2);
+    }
+
+    #[test]
+    fn decodes_compressed_responses() {
+        // partial_success { rejected_data_points: 2, error_message: "bad" }
+        let partial = vec![0x0a, 7, 0x08, 2, 0x12, 3, b'b', b'a', b'd'];
+        let (endpoint, _received) =
+            mock_collector(vec![(STATUS_OK, partial.clone())], ReplyEncoding::Gzip);
+        let exporter = OtlpGrpcExporter::builder()
+            .with_endpoint(&endpoint)
+            .with_compression(Compression::Gzip)
+            .build()
+            .unwrap();
+        assert_eq!(
+            exporter
+                .export(&empty_metrics(), Duration::from_secs(5))
+                .err(),
+            Some(MetricsError::Export(
+                "collector rejected 2 data points: bad".into()
+            ))
+        );
+
+        let (endpoint, _received) =
+            mock_collector(vec![(STATUS_OK, partial)], ReplyEncoding::Undeclared);
+        let exporter = OtlpGrpcExporter::builder()
+            .with_endpoint(&endpoint)
+            .build()
+            .unwrap();
+        assert_eq!(
+            exporter
+                .export(&empty_metrics(), Duration::from_secs(5))
+                .err(),
+            Some(MetricsError::Export(
+                "collector sent a compressed response with unsupported grpc-encoding \"\"".into()
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn exports_from_async_context() {
+        let (endpoint, received) =
+            mock_collector(vec![(STATUS_OK, vec![])], ReplyEncoding::Identity);
+        let exporter = OtlpGrpcExporter::builder()
+            .with_endpoint(&endpoint)
+            .build()
+            .unwrap();
+        exporter
+            .export(&empty_metrics(), Duration::from_secs(5))
+            .unwrap();
+        assert!(received.recv().is_ok());
+        drop(exporter);
+    }
+}
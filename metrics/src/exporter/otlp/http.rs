@@ -0,0 +1,310 @@
+use std::{
+    io, thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    error::{MetricsError, MetricsResult},
+    exporter::{
+        http_client::{self, gzip, Endpoint, Response},
+        PushMetricExporter,
+    },
+    instrument::InstrumentKind,
+    metric::{ResourceMetrics, Temporality},
+};
+
+use super::{backoff, proto, Compression, OtlpConfig};
+
+const DEFAULT_ENDPOINT: &str = "http://localhost:4318/v1/metrics";
+/// Appended to `OTEL_EXPORTER_OTLP_ENDPOINT`, which names the collector
+/// rather than the metrics endpoint.
+const HTTP_SIGNAL_PATH: &str = "/v1/metrics";
+
+/// Pushes collections to an OTLP/HTTP endpoint as protobuf.
+///
+/// Requests go over plain HTTP/1.1; terminate TLS in a local collector or
+/// proxy. Connection failures and responses with status 429, 502, 503 or
+/// 504 are retried with exponential backoff, honoring `Retry-After`, for as
+/// long as the export timeout allows.
+///
+/// ```no_run
+/// use metrics::exporter::OtlpHttpExporter;
+/// use metrics::periodic_reader::PeriodicReader;
+///
+/// let exporter = OtlpHttpExporter::builder()
+///     .with_endpoint("http://collector:4318/v1/metrics")
+///     .with_header("x-tenant", "checkout")
+///     .build()
+///     .unwrap();
+/// let reader = PeriodicReader::new(exporter);
+/// ```
+#[derive(Debug)]
+pub struct OtlpHttpExporter {
+    endpoint: Endpoint,
+    config: OtlpConfig,
+}
+
+/// Configures an [`OtlpHttpExporter`]. Unset settings come from the
+/// `OTEL_EXPORTER_OTLP_*` environment variables.
+#[derive(Debug, Default)]
+pub struct OtlpHttpExporterBuilder {
+    endpoint: Option<String>,
+    config: OtlpConfig,
+}
+
+impl OtlpHttpExporter {
+    pub fn builder() -> OtlpHttpExporterBuilder {
+        OtlpHttpExporterBuilder::default()
+    }
+}
+
+impl OtlpHttpExporterBuilder {
+    /// Full URL of the metrics endpoint, `http://localhost:4318/v1/metrics`
+    /// by default.
+    pub fn with_endpoint(mut self, endpoint: &str) -> Self {
+        self.endpoint = Some(endpoint.to_string());
+        self
+    }
+
+    /// Adds a header sent with every request.
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.config
+            .headers
+            .push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.config.compression = Some(compression);
+        self
+    }
+
+    /// Temporality requested for every instrument. Defaults to cumulative.
+    pub fn with_temporality(mut self, temporality: Temporality) -> Self {
+        self.config.temporality = Some(temporality);
+        self
+    }
+
+    /// Upper bound for a single export, including retries. The reader's
+    /// export timeout applies when it is shorter.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeout = Some(timeout);
+        self
+    }
+
+    pub fn build(self) -> MetricsResult<OtlpHttpExporter> {
+        let env = super::env_settings(Some(HTTP_SIGNAL_PATH))?;
+        let endpoint = self
+            .endpoint
+            .or(env.endpoint)
+            .unwrap_or_else(|| DEFAULT_ENDPOINT.to_string());
+        Ok(OtlpHttpExporter {
+            endpoint: Endpoint::parse(&endpoint)?,
+            config: self.config.or(env.config),
+        })
+    }
+}
+
+impl PushMetricExporter for OtlpHttpExporter {
+    fn export(&self, metrics: &ResourceMetrics, timeout: Duration) -> MetricsResult<()> {
+        let body = proto::encode_request(metrics);
+        let body = match self.config.compression() {
+            Compression::Gzip => gzip(&body).map_err(export_error)?,
+            Compression::None => body,
+        };
+        let deadline = Instant::now() + self.config.timeout(timeout);
+        let mut attempt = 0;
+        loop {
+            let response = match self.send(&body, deadline) {
+                Ok(response) => response,
+                Err(err) => {
+                    let delay = backoff(attempt, None);
+                    if Instant::now() + delay >= deadline {
+                        return Err(export_error(err));
+                    }
+                    thread::sleep(delay);
+                    attempt += 1;
+                    continue;
+                }
+            };
+            if (200..300).contains(&response.status) {
+                return match proto::decode_partial_success(&response.body) {
+                    Some((rejected, message)) => Err(MetricsError::Export(format!(
+                        "collector rejected {} data points: {}",
+                        rejected, message
+                    ))),
+                    None => Ok(()),
+                };
+            }
+            let retryable = matches!(response.status, 429 | 502 | 503 | 504);
+            let delay = backoff(attempt, response.retry_after);
+            if !retryable || Instant::now() + delay >= deadline {
+                return Err(MetricsError::Export(format!(
+                    "collector responded with status {}: {}",
+                    response.status,
+                    String::from_utf8_lossy(&response.body).trim()
+                )));
+            }
+            thread::sleep(delay);
+            attempt += 1;
+        }
+    }
+
+    fn temporality(&self, _kind: InstrumentKind) -> Temporality {
+        self.config.temporality()
+    }
+}
+
+impl OtlpHttpExporter {
+    fn send(&self, body: &[u8], deadline: Instant) -> io::Result<Response> {
+        let mut headers = vec![("Content-Type", "application/x-protobuf")];
+        if self.config.compression() == Compression::Gzip {
+            headers.push(("Content-Encoding", "gzip"));
+        }
+        for (name, value) in &self.config.headers {
+            headers.push((name, value));
+        }
+        http_client::post(&self.endpoint, &headers, body, deadline)
+    }
+}
+
+fn export_error(err: io::Error) -> MetricsError {
+    MetricsError::Export(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meter_provider::MeterProvider;
+    use crate::periodic_reader::PeriodicReader;
+    use crate::reader::MetricReader;
+    use flate2::read::GzDecoder;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+
+    struct Received {
+        head: String,
+        body: Vec<u8>,
+    }
+
+    /// Answers each connection with the next of `statuses`, reporting every
+    /// request it receives.
+    fn mock_collector(statuses: Vec<&'static str>) -> (String, mpsc::Receiver<Received>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/v1/metrics", listener.local_addr().unwrap());
+        (endpoint, serve(listener, statuses))
+    }
+
+    fn serve(listener: TcpListener, statuses: Vec<&'static str>) -> mpsc::Receiver<Received> {
+        let (requests, received) = mpsc::channel();
+        thread::spawn(move || {
+            for status in statuses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut head = String::new();
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                        length = value.trim().parse().unwrap();
+                    }
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    head.push_str(&line);
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                requests.send(Received { head, body }).unwrap();
+                write!(
+                    reader.get_mut(),
+                    "HTTP/1.1 {}\r\nRetry-After: 0\r\nContent-Length: 0\r\n\r\n",
+                    status
+                )
+                .unwrap();
+            }
+        });
+        received
+    }
+
+    #[test]
+    fn posts_protobuf_with_headers_and_gzip() {
+        // The last reply is for the export on shutdown.
+        let (endpoint, received) =
+            mock_collector(vec!["503 Service Unavailable", "200 OK", "200 OK"]);
+        let exporter = OtlpHttpExporter::builder()
+            .with_endpoint(&endpoint)
+            .with_header("x-tenant", "checkout")
+            .with_compression(Compression::Gzip)
+            .build()
+            .unwrap();
+        let reader = PeriodicReader::new(exporter);
+        let provider = MeterProvider::builder()
+            .with_reader(reader.clone())
+            .build()
+            .unwrap();
+        provider
+            .get_meter("meter")
+            .create_counter("requests")
+            .add(1, &[]);
+        reader.force_flush(Duration::from_secs(5)).unwrap();
+
+        let first = received.recv().unwrap();
+        let retried = received.recv().unwrap();
+        assert_eq!(first.body, retried.body);
+        assert!(retried.head.starts_with("POST /v1/metrics HTTP/1.1"));
+        assert!(retried
+            .head
+            .contains("Content-Type: application/x-protobuf"));
+        assert!(retried.head.contains("Content-Encoding: gzip"));
+        assert!(retried.head.contains("x-tenant: checkout"));
+        let mut decoded = vec![];
+        GzDecoder::new(retried.body.as_slice())
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert!(decoded.windows(8).any(|window| window == b"requests"));
+    }
+
+    #[test]
+    fn does_not_retry_client_errors() {
+        let (endpoint, received) = mock_collector(vec!["400 Bad Request"]);
+        let exporter = OtlpHttpExporter::builder()
+            .with_endpoint(&endpoint)
+            .build()
+            .unwrap();
+        let metrics = ResourceMetrics {
+            resource: crate::resource::Resource::empty(),
+            scope_metrics: vec![],
+        };
+        let result = exporter.export(&metrics, Duration::from_secs(5));
+        assert!(matches!(result, Err(MetricsError::Export(_))));
+        assert!(received.recv().is_ok());
+    }
+
+    #[test]
+    fn retries_refused_connections() {
+        // Nothing listens on the port until the collector comes up.
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let (started, received) = mpsc::channel();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(300));
+            let listener = TcpListener::bind(addr).unwrap();
+            started.send(serve(listener, vec!["200 OK"])).unwrap();
+        });
+        let exporter = OtlpHttpExporter::builder()
+            .with_endpoint(&format!("http://{}/v1/metrics", addr))
+            .build()
+            .unwrap();
+        let metrics = ResourceMetrics {
+            resource: crate::resource::Resource::empty(),
+            scope_metrics: vec![],
+        };
+        exporter.export(&metrics, Duration::from_secs(10)).unwrap();
+        assert!(received.recv().unwrap().recv().is_ok());
+    }
+}
@@ -0,0 +1,245 @@
+//! OTLP-JSON encoding of `ExportMetricsServiceRequest`, following the
+//! protobuf JSON mapping: camelCase field names, enums as numbers and 64-bit
+//! integers as decimal strings.
+
+use serde_json::{json, Map, Value as Json};
+
+use crate::{
+    common::{Array, KeyValue, Value},
+    metric::{
+        DataPoint, ExponentialHistogramDataPoint, HistogramDataPoint, Metric, MetricData,
+        ResourceMetrics, ScopeMetrics, Temporality,
+    },
+    scope::InstrumentationScope,
+};
+
+use super::proto::unix_nanos;
+
+/// Encodes `metrics` as an `ExportMetricsServiceRequest`.
+pub(crate) fn encode_request(metrics: &ResourceMetrics) -> Json {
+    json!({
+        "resourceMetrics": [{
+            "resource": { "attributes": attributes(metrics.resource.iter()) },
+            "scopeMetrics": metrics.scope_metrics.iter().map(scope_metrics).collect::<Vec<_>>(),
+        }]
+    })
+}
+
+fn scope_metrics(scope_metrics: &ScopeMetrics) -> Json {
+    json!({
+        "scope": scope(&scope_metrics.scope),
+        "metrics": scope_metrics.metrics.iter().map(metric).collect::<Vec<_>>(),
+        "schemaUrl": scope_metrics.scope.schema_url().unwrap_or_default(),
+    })
+}
+
+fn scope(scope: &InstrumentationScope) -> Json {
+    json!({
+        "name": scope.name(),
+        "version": scope.version().unwrap_or_default(),
+        "attributes": attributes(scope.attributes().iter()),
+    })
+}
+
+fn metric(metric: &Metric) -> Json {
+    let (field, data) = match &metric.data {
+        MetricData::Gauge(gauge) => (
+            "gauge",
+            json!({ "dataPoints": gauge.data_points.iter().map(number_point).collect::<Vec<_>>() }),
+        ),
+        MetricData::Sum(sum) => (
+            "sum",
+            json!({
+                "dataPoints": sum.data_points.iter().map(number_point).collect::<Vec<_>>(),
+                "aggregationTemporality": temporality(sum.temporality),
+                "isMonotonic": sum.is_monotonic,
+            }),
+        ),
+        MetricData::Histogram(histogram) => (
+            "histogram",
+            json!({
+                "dataPoints": histogram.data_points.iter().map(histogram_point).collect::<Vec<_>>(),
+                "aggregationTemporality": temporality(histogram.temporality),
+            }),
+        ),
+        MetricData::ExponentialHistogram(histogram) => (
+            "exponentialHistogram",
+            json!({
+                "dataPoints": histogram.data_points.iter().map(exponential_point).collect::<Vec<_>>(),
+                "aggregationTemporality": temporality(histogram.temporality),
+            }),
+        ),
+    };
+    let mut metric = json!({
+        "name": metric.name,
+        "description": metric.description,
+        "unit": metric.unit,
+    });
+    metric[field] = data;
+    metric
+}
+
+fn temporality(temporality: Temporality) -> u8 {
+    match temporality {
+        Temporality::Delta => 1,
+        Temporality::Cumulative => 2,
+    }
+}
+
+fn number_point(point: &DataPoint) -> Json {
+    json!({
+        "attributes": attributes(point.attributes.iter()),
+        "startTimeUnixNano": unix_nanos(point.start_time).to_string(),
+        "timeUnixNano": unix_nanos(point.time).to_string(),
+        "asInt": point.value.to_string(),
+    })
+}
+
+fn histogram_point(point: &HistogramDataPoint) -> Json {
+    let mut json = json!({
+        "attributes": attributes(point.attributes.iter()),
+        "startTimeUnixNano": unix_nanos(point.start_time).to_string(),
+        "timeUnixNano": unix_nanos(point.time).to_string(),
+        "count": point.count.to_string(),
+        "sum": point.sum as f64,
+        "bucketCounts": point.bucket_counts.iter().map(u64::to_string).collect::<Vec<_>>(),
+        "explicitBounds": point.bounds.iter().map(|bound| double(*bound)).collect::<Vec<_>>(),
+    });
+    min_max(&mut json, point.min, point.max);
+    json
+}
+
+fn exponential_point(point: &ExponentialHistogramDataPoint) -> Json {
+    let mut json = json!({
+        "attributes": attributes(point.attributes.iter()),
+        "startTimeUnixNano": unix_nanos(point.start_time).to_string(),
+        "timeUnixNano": unix_nanos(point.time).to_string(),
+        "count": point.count.to_string(),
+        "sum": point.sum as f64,
+        "scale": point.scale,
+        "zeroCount": point.zero_count.to_string(),
+        "positive": {
+            "offset": point.positive_offset,
+            "bucketCounts": point.positive_bucket_counts.iter().map(u64::to_string).collect::<Vec<_>>(),
+        },
+    });
+    min_max(&mut json, point.min, point.max);
+    json
+}
+
+fn min_max(json: &mut Json, min: Option<u64>, max: Option<u64>) {
+    if let Some(min) = min {
+        json["min"] = json!(min as f64);
+    }
+    if let Some(max) = max {
+        json["max"] = json!(max as f64);
+    }
+}
+
+fn attributes<'a>(attributes: impl Iterator<Item = &'a KeyValue>) -> Vec<Json> {
+    attributes
+        .map(|kv| json!({ "key": kv.key.as_str(), "value": any_value(&kv.value) }))
+        .collect()
+}
+
+fn any_value(value: &Value) -> Json {
+    let (field, value) = match value {
+        Value::String(value) => ("stringValue", json!(value.as_str())),
+        Value::Bool(value) => ("boolValue", json!(value)),
+        Value::I64(value) => ("intValue", json!(value.to_string())),
+        Value::F64(value) => ("doubleValue", double(*value)),
+        Value::Array(array) => {
+            let values: Vec<Json> = match array {
+                Array::Bool(values) => values.iter().map(|v| any_value(&Value::Bool(*v))).collect(),
+                Array::I64(values) => values.iter().map(|v| any_value(&Value::I64(*v))).collect(),
+                Array::F64(values) => values.iter().map(|v| any_value(&Value::F64(*v))).collect(),
+                Array::String(values) => values
+                    .iter()
+                    .map(|v| any_value(&Value::String(v.clone())))
+                    .collect(),
+            };
+            ("arrayValue", json!({ "values": values }))
+        }
+    };
+    let mut any = Map::new();
+    any.insert(field.to_string(), value);
+    Json::Object(any)
+}
+
+/// Non-finite doubles are spelled as strings in the JSON mapping.
+fn double(value: f64) -> Json {
+    if value.is_nan() {
+        json!("NaN")
+    } else if value.is_infinite() {
+        json!(if value > 0.0 { "Infinity" } else { "-Infinity" })
+    } else {
+        json!(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{metric::Sum, resource::Resource};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn encodes_request() {
+        let metrics = ResourceMetrics {
+            resource: Resource::new([KeyValue::new("service.name", "checkout")]),
+            scope_metrics: vec![ScopeMetrics {
+                scope: InstrumentationScope::new("http").with_version("1.0"),
+                metrics: vec![Metric::new(
+                    "requests".into(),
+                    String::new(),
+                    "{request}".into(),
+                    MetricData::Sum(Sum {
+                        data_points: vec![DataPoint {
+                            attributes: vec![
+                                KeyValue::new("code", 200_i64),
+                                KeyValue::new("ratio", f64::NAN),
+                            ],
+                            start_time: UNIX_EPOCH + Duration::from_secs(1),
+                            time: UNIX_EPOCH + Duration::from_secs(2),
+                            value: 7,
+                        }],
+                        temporality: Temporality::Cumulative,
+                        is_monotonic: true,
+                    }),
+                )],
+            }],
+        };
+        assert_eq!(
+            encode_request(&metrics),
+            json!({
+                "resourceMetrics": [{
+                    "resource": {
+                        "attributes": [{ "key": "service.name", "value": { "stringValue": "checkout" } }]
+                    },
+                    "scopeMetrics": [{
+                        "scope": { "name": "http", "version": "1.0", "attributes": [] },
+                        "metrics": [{
+                            "name": "requests",
+                            "description": "",
+                            "unit": "{request}",
+                            "sum": {
+                                "dataPoints": [{
+                                    "attributes": [
+                                        { "key": "code", "value": { "intValue": "200" } },
+                                        { "key": "ratio", "value": { "doubleValue": "NaN" } },
+                                    ],
+                                    "startTimeUnixNano": "1000000000",
+                                    "timeUnixNano": "2000000000",
+                                    "asInt": "7",
+                                }],
+                                "aggregationTemporality": 2,
+                                "isMonotonic": true,
+                            },
+                        }],
+                        "schemaUrl": "",
+                    }],
+                }]
+            })
+        );
+    }
+}
@@ -0,0 +1,213 @@
+//! Exporters speaking the OpenTelemetry protocol. The gRPC exporter needs
+//! the `grpc` feature, which is on by default.
+
+use std::time::Duration;
+
+use rand::Rng;
+
+use crate::{
+    env_config::{self, *},
+    error::MetricsResult,
+    metric::Temporality,
+    resource::percent_decode,
+};
+
+mod file;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod http;
+pub(crate) mod json;
+pub(crate) mod proto;
+
+pub use file::{OtlpFileExporter, OtlpFileExporterBuilder};
+#[cfg(feature = "grpc")]
+pub use grpc::{OtlpGrpcExporter, OtlpGrpcExporterBuilder};
+pub use http::{OtlpHttpExporter, OtlpHttpExporterBuilder};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+const INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+const MAX_BACKOFF: Duration = Duration::from_secs(5);
+
+/// Compression applied to export requests.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Compression {
+    #[default]
+    None,
+    Gzip,
+}
+
+/// Settings shared by the OTLP exporters; unset fields fall back to the
+/// environment and then to the defaults.
+#[derive(Debug, Default)]
+pub(crate) struct OtlpConfig {
+    pub(crate) headers: Vec<(String, String)>,
+    pub(crate) compression: Option<Compression>,
+    pub(crate) temporality: Option<Temporality>,
+    pub(crate) timeout: Option<Duration>,
+}
+
+impl OtlpConfig {
+    /// Fills the fields left unset from `fallback`. Headers are merged, with
+    /// names already present taking precedence.
+    pub(crate) fn or(mut self, fallback: OtlpConfig) -> OtlpConfig {
+        for (name, value) in fallback.headers {
+            if !self
+                .headers
+                .iter()
+                .any(|(existing, _)| existing.eq_ignore_ascii_case(&name))
+            {
+                self.headers.push((name, value));
+            }
+        }
+        OtlpConfig {
+            headers: self.headers,
+            compression: self.compression.or(fallback.compression),
+            temporality: self.temporality.or(fallback.temporality),
+            timeout: self.timeout.or(fallback.timeout),
+        }
+    }
+
+    pub(crate) fn compression(&self) -> Compression {
+        self.compression.unwrap_or_default()
+    }
+
+    pub(crate) fn temporality(&self) -> Temporality {
+        self.temporality.unwrap_or(Temporality::Cumulative)
+    }
+
+    /// Time allowed for one export, never more than the reader's timeout.
+    pub(crate) fn timeout(&self, reader_timeout: Duration) -> Duration {
+        self.timeout.unwrap_or(DEFAULT_TIMEOUT).min(reader_timeout)
+    }
+}
+
+pub(crate) struct EnvSettings {
+    pub(crate) endpoint: Option<String>,
+    pub(crate) config: OtlpConfig,
+}
+
+/// Reads the `OTEL_EXPORTER_OTLP_*` variables, preferring the metrics-specific
+/// ones. `signal_path` is appended to the generic endpoint.
+pub(crate) fn env_settings(signal_path: Option<&str>) -> MetricsResult<EnvSettings> {
+    let endpoint = match env_config::read_env(OTEL_EXPORTER_OTLP_METRICS_ENDPOINT, parse_string)? {
+        Some(endpoint) => Some(endpoint),
+        None => env_config::read_env(OTEL_EXPORTER_OTLP_ENDPOINT, parse_string)?.map(|base| {
+            match signal_path {
+                Some(path) => format!("{}{}", base.trim_end_matches('/'), path),
+                None => base,
+            }
+        }),
+    };
+    Ok(EnvSettings {
+        endpoint,
+        config: OtlpConfig {
+            headers: read_either(
+                OTEL_EXPORTER_OTLP_METRICS_HEADERS,
+                OTEL_EXPORTER_OTLP_HEADERS,
+                parse_headers,
+            )?
+            .unwrap_or_default(),
+            compression: read_either(
+                OTEL_EXPORTER_OTLP_METRICS_COMPRESSION,
+                OTEL_EXPORTER_OTLP_COMPRESSION,
+                parse_compression,
+            )?,
+            temporality: env_config::read_env(
+                OTEL_EXPORTER_OTLP_METRICS_TEMPORALITY_PREFERENCE,
+                env_config::parse_temporality,
+            )?,
+            timeout: read_either(
+                OTEL_EXPORTER_OTLP_METRICS_TIMEOUT,
+                OTEL_EXPORTER_OTLP_TIMEOUT,
+                env_config::parse_millis,
+            )?,
+        },
+    })
+}
+
+fn read_either<T>(
+    specific: &str,
+    generic: &str,
+    parse: impl Fn(&str) -> Option<T> + Copy,
+) -> MetricsResult<Option<T>> {
+    match env_config::read_env(specific, parse)? {
+        Some(value) => Ok(Some(value)),
+        None => env_config::read_env(generic, parse),
+    }
+}
+
+fn parse_string(value: &str) -> Option<String> {
+    Some(value.to_string())
+}
+
+fn parse_headers(value: &str) -> Option<Vec<(String, String)>> {
+    value
+        .split(',')
+        .filter(|pair| !pair.trim().is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=')?;
+            let name = percent_decode(name.trim())?;
+            if name.is_empty() {
+                return None;
+            }
+            Some((name, percent_decode(value.trim())?))
+        })
+        .collect()
+}
+
+fn parse_compression(value: &str) -> Option<Compression> {
+    match value.to_ascii_lowercase().as_str() {
+        "gzip" => Some(Compression::Gzip),
+        "none" => Some(Compression::None),
+        _ => None,
+    }
+}
+
+/// Delay before retry number `attempt + 1`: exponential with jitter, unless
+/// the server asked for a specific delay.
+pub(crate) fn backoff(attempt: u32, retry_after: Option<Duration>) -> Duration {
+    if let Some(retry_after) = retry_after {
+        return retry_after;
+    }
+    let delay = INITIAL_BACKOFF
+        .saturating_mul(1 << attempt.min(16))
+        .min(MAX_BACKOFF);
+    delay.mul_f64(rand::thread_rng().gen_range(0.5..1.5))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_config_and_parses_env_values() {
+        let explicit = OtlpConfig {
+            headers: vec![("Authorization".into(), "explicit".into())],
+            timeout: Some(Duration::from_secs(60)),
+            ..Default::default()
+        };
+        let env = OtlpConfig {
+            headers: parse_headers("authorization=env, x-tenant=a%20b").unwrap(),
+            compression: parse_compression("GZIP"),
+            ..Default::default()
+        };
+        let config = explicit.or(env);
+        assert_eq!(
+            config.headers,
+            vec![
+                ("Authorization".to_string(), "explicit".to_string()),
+                ("x-tenant".to_string(), "a b".to_string()),
+            ]
+        );
+        assert_eq!(config.compression(), Compression::Gzip);
+        assert_eq!(config.temporality(), Temporality::Cumulative);
+        assert_eq!(
+            config.timeout(Duration::from_secs(30)),
+            Duration::from_secs(30)
+        );
+        assert_eq!(parse_headers("novalue"), None);
+
+        assert_eq!(backoff(3, Some(Duration::ZERO)), Duration::ZERO);
+        assert!(backoff(40, None) < MAX_BACKOFF.mul_f64(1.5));
+    }
+}
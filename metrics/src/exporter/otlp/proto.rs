@@ -0,0 +1,423 @@
+//! Protobuf encoding of `ExportMetricsServiceRequest`, following the
+//! `opentelemetry/proto/collector/metrics/v1` definitions, and decoding of
+//! the response's partial success.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{
+    common::{Array, KeyValue, Value},
+    metric::{
+        DataPoint, ExponentialHistogramDataPoint, HistogramDataPoint, Metric, MetricData,
+        ResourceMetrics, ScopeMetrics, Temporality,
+    },
+    resource::Resource,
+    scope::InstrumentationScope,
+};
+
+const VARINT: u8 = 0;
+const FIXED64: u8 = 1;
+const LENGTH_DELIMITED: u8 = 2;
+
+/// Encodes `metrics` as an `ExportMetricsServiceRequest`.
+pub(crate) fn encode_request(metrics: &ResourceMetrics) -> Vec<u8> {
+    let mut request = Writer::default();
+    request.message(1, |w| resource_metrics(w, metrics));
+    request.buf
+}
+
+/// Rejected data points and error message of an
+/// `ExportMetricsServiceResponse`, if it reports a partial success.
+pub(crate) fn decode_partial_success(response: &[u8]) -> Option<(i64, String)> {
+    let partial_success = Reader::new(response).find_bytes(1)?;
+    let mut rejected = 0;
+    let mut message = String::new();
+    let mut reader = Reader::new(partial_success);
+    while let Some((field, value)) = reader.next_field() {
+        match (field, value) {
+            (1, Field::Varint(value)) => rejected = value as i64,
+            (2, Field::Bytes(bytes)) => message = String::from_utf8_lossy(bytes).into_owned(),
+            _ => {}
+        }
+    }
+    (rejected != 0 || !message.is_empty()).then_some((rejected, message))
+}
+
+fn resource_metrics(w: &mut Writer, metrics: &ResourceMetrics) {
+    w.message(1, |w| resource(w, &metrics.resource));
+    for scope_metrics in &metrics.scope_metrics {
+        w.message(2, |w| scope_metrics_message(w, scope_metrics));
+    }
+}
+
+fn resource(w: &mut Writer, resource: &Resource) {
+    for kv in resource.iter() {
+        w.message(1, |w| key_value(w, kv));
+    }
+}
+
+fn scope_metrics_message(w: &mut Writer, scope_metrics: &ScopeMetrics) {
+    w.message(1, |w| scope(w, &scope_metrics.scope));
+    for metric in &scope_metrics.metrics {
+        w.message(2, |w| metric_message(w, metric));
+    }
+    w.string(3, scope_metrics.scope.schema_url().unwrap_or_default());
+}
+
+fn scope(w: &mut Writer, scope: &InstrumentationScope) {
+    w.string(1, scope.name());
+    w.string(2, scope.version().unwrap_or_default());
+    for kv in scope.attributes() {
+        w.message(3, |w| key_value(w, kv));
+    }
+}
+
+fn metric_message(w: &mut Writer, metric: &Metric) {
+    w.string(1, &metric.name);
+    w.string(2, &metric.description);
+    w.string(3, &metric.unit);
+    match &metric.data {
+        MetricData::Gauge(gauge) => w.message(5, |w| {
+            for point in &gauge.data_points {
+                w.message(1, |w| number_point(w, point));
+            }
+        }),
+        MetricData::Sum(sum) => w.message(7, |w| {
+            for point in &sum.data_points {
+                w.message(1, |w| number_point(w, point));
+            }
+            w.uint64(2, temporality(sum.temporality));
+            w.uint64(3, sum.is_monotonic as u64);
+        }),
+        MetricData::Histogram(histogram) => w.message(9, |w| {
+            for point in &histogram.data_points {
+                w.message(1, |w| histogram_point(w, point));
+            }
+            w.uint64(2, temporality(histogram.temporality));
+        }),
+        MetricData::ExponentialHistogram(histogram) => w.message(10, |w| {
+            for point in &histogram.data_points {
+                w.message(1, |w| exponential_point(w, point));
+            }
+            w.uint64(2, temporality(histogram.temporality));
+        }),
+    }
+}
+
+fn temporality(temporality: Temporality) -> u64 {
+    match temporality {
+        Temporality::Delta => 1,
+        Temporality::Cumulative => 2,
+    }
+}
+
+fn number_point(w: &mut Writer, point: &DataPoint) {
+    w.fixed64(2, unix_nanos(point.start_time));
+    w.fixed64(3, unix_nanos(point.time));
+    // as_int is an sfixed64
+    w.tag(6, FIXED64);
+    w.buf.extend_from_slice(&point.value.to_le_bytes());
+    for kv in &point.attributes {
+        w.message(7, |w| key_value(w, kv));
+    }
+}
+
+fn histogram_point(w: &mut Writer, point: &HistogramDataPoint) {
+    w.fixed64(2, unix_nanos(point.start_time));
+    w.fixed64(3, unix_nanos(point.time));
+    w.fixed64(4, point.count);
+    w.double(5, point.sum as f64);
+    w.packed(6, point.bucket_counts.iter(), |buf, count| {
+        buf.extend_from_slice(&count.to_le_bytes())
+    });
+    w.packed(7, point.bounds.iter(), |buf, bound| {
+        buf.extend_from_slice(&bound.to_le_bytes())
+    });
+    for kv in &point.attributes {
+        w.message(9, |w| key_value(w, kv));
+    }
+    if let Some(min) = point.min {
+        w.double(11, min as f64);
+    }
+    if let Some(max) = point.max {
+        w.double(12, max as f64);
+    }
+}
+
+fn exponential_point(w: &mut Writer, point: &ExponentialHistogramDataPoint) {
+    for kv in &point.attributes {
+        w.message(1, |w| key_value(w, kv));
+    }
+    w.fixed64(2, unix_nanos(point.start_time));
+    w.fixed64(3, unix_nanos(point.time));
+    w.fixed64(4, point.count);
+    w.double(5, point.sum as f64);
+    w.sint32(6, point.scale as i32);
+    w.fixed64(7, point.zero_count);
+    w.message(8, |w| {
+        w.sint32(1, point.positive_offset);
+        w.packed(2, point.positive_bucket_counts.iter(), |buf, count| {
+            write_varint(buf, *count)
+        });
+    });
+    if let Some(min) = point.min {
+        w.double(12, min as f64);
+    }
+    if let Some(max) = point.max {
+        w.double(13, max as f64);
+    }
+}
+
+fn key_value(w: &mut Writer, kv: &KeyValue) {
+    w.string(1, kv.key.as_str());
+    w.message(2, |w| any_value(w, &kv.value));
+}
+
+fn any_value(w: &mut Writer, value: &Value) {
+    match value {
+        Value::String(value) => {
+            w.tag(1, LENGTH_DELIMITED);
+            w.bytes(value.as_str().as_bytes());
+        }
+        Value::Bool(value) => {
+            w.tag(2, VARINT);
+            write_varint(&mut w.buf, *value as u64);
+        }
+        Value::I64(value) => {
+            w.tag(3, VARINT);
+            write_varint(&mut w.buf, *value as u64);
+        }
+        Value::F64(value) => {
+            w.tag(4, FIXED64);
+            w.buf.extend_from_slice(&value.to_le_bytes());
+        }
+        Value::Array(array) => w.message(5, |w| {
+            let values: Vec<Value> = match array {
+                Array::Bool(values) => values.iter().map(|v| Value::Bool(*v)).collect(),
+                Array::I64(values) => values.iter().map(|v| Value::I64(*v)).collect(),
+                Array::F64(values) => values.iter().map(|v| Value::F64(*v)).collect(),
+                Array::String(values) => values.iter().cloned().map(Value::String).collect(),
+            };
+            for value in &values {
+                w.message(1, |w| any_value(w, value));
+            }
+        }),
+    }
+}
+
+pub(crate) fn unix_nanos(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|since_epoch| since_epoch.as_nanos() as u64)
+        .unwrap_or_default()
+}
+
+fn write_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+/// Appends protobuf fields to a buffer. Scalar fields holding their default
+/// value are left out, as proto3 does.
+#[derive(Default)]
+struct Writer {
+    buf: Vec<u8>,
+}
+
+impl Writer {
+    fn tag(&mut self, field: u32, wire_type: u8) {
+        write_varint(&mut self.buf, ((field << 3) | wire_type as u32) as u64);
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        write_varint(&mut self.buf, bytes.len() as u64);
+        self.buf.extend_from_slice(bytes);
+    }
+
+    fn uint64(&mut self, field: u32, value: u64) {
+        if value != 0 {
+            self.tag(field, VARINT);
+            write_varint(&mut self.buf, value);
+        }
+    }
+
+    fn sint32(&mut self, field: u32, value: i32) {
+        self.uint64(field, ((value << 1) ^ (value >> 31)) as u32 as u64);
+    }
+
+    fn fixed64(&mut self, field: u32, value: u64) {
+        if value != 0 {
+            self.tag(field, FIXED64);
+            self.buf.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    /// Always written: the fields using it are `optional`.
+    fn double(&mut self, field: u32, value: f64) {
+        self.tag(field, FIXED64);
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn string(&mut self, field: u32, value: &str) {
+        if !value.is_empty() {
+            self.tag(field, LENGTH_DELIMITED);
+            self.bytes(value.as_bytes());
+        }
+    }
+
+    fn message(&mut self, field: u32, encode: impl FnOnce(&mut Writer)) {
+        let mut nested = Writer::default();
+        encode(&mut nested);
+        self.tag(field, LENGTH_DELIMITED);
+        self.bytes(&nested.buf);
+    }
+
+    fn packed<T>(
+        &mut self,
+        field: u32,
+        values: impl ExactSizeIterator<Item = T>,
+        encode: impl Fn(&mut Vec<u8>, T),
+    ) {
+        if values.len() == 0 {
+            return;
+        }
+        let mut packed = vec![];
+        for value in values {
+            encode(&mut packed, value);
+        }
+        self.tag(field, LENGTH_DELIMITED);
+        self.bytes(&packed);
+    }
+}
+
+enum Field<'a> {
+    Varint(u64),
+    Fixed64,
+    Bytes(&'a [u8]),
+    Fixed32,
+}
+
+/// Walks the top-level fields of an encoded message.
+struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(buf: &'a [u8]) -> Reader<'a> {
+        Reader { buf }
+    }
+
+    fn find_bytes(mut self, wanted: u32) -> Option<&'a [u8]> {
+        while let Some((field, value)) = self.next_field() {
+            if let (true, Field::Bytes(bytes)) = (field == wanted, value) {
+                return Some(bytes);
+            }
+        }
+        None
+    }
+
+    /// The next field, or `None` at the end or on malformed input.
+    fn next_field(&mut self) -> Option<(u32, Field<'a>)> {
+        let key = self.varint()?;
+        let field = (key >> 3) as u32;
+        let value = match key & 0x7 {
+            0 => Field::Varint(self.varint()?),
+            1 => {
+                self.advance(8)?;
+                Field::Fixed64
+            }
+            2 => {
+                let len = self.varint()? as usize;
+                Field::Bytes(self.advance(len)?)
+            }
+            5 => {
+                self.advance(4)?;
+                Field::Fixed32
+            }
+            _ => return None,
+        };
+        Some((field, value))
+    }
+
+    fn varint(&mut self) -> Option<u64> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let (&byte, rest) = self.buf.split_first()?;
+            self.buf = rest;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte < 0x80 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    fn advance(&mut self, len: usize) -> Option<&'a [u8]> {
+        if len > self.buf.len() {
+            return None;
+        }
+        let (taken, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        Some(taken)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metric::Sum;
+    use std::time::Duration;
+
+    #[test]
+    fn encodes_request() {
+        let metrics = ResourceMetrics {
+            resource: Resource::new([KeyValue::new("service.name", "a")]),
+            scope_metrics: vec![ScopeMetrics {
+                scope: InstrumentationScope::new("m"),
+                metrics: vec![Metric::new(
+                    "c".into(),
+                    String::new(),
+                    String::new(),
+                    MetricData::Sum(Sum {
+                        data_points: vec![DataPoint {
+                            attributes: vec![],
+                            start_time: UNIX_EPOCH,
+                            time: UNIX_EPOCH + Duration::from_nanos(1),
+                            value: 300,
+                        }],
+                        temporality: Temporality::Cumulative,
+                        is_monotonic: true,
+                    }),
+                )],
+            }],
+        };
+        #[rustfmt::skip]
+        let expected: Vec<u8> = vec![
+            0x0a, 61, // resource_metrics
+                0x0a, 21, // resource
+                    0x0a, 19, // attribute
+                        0x0a, 12, b's', b'e', b'r', b'v', b'i', b'c', b'e', b'.', b'n', b'a', b'm', b'e',
+                        0x12, 3, 0x0a, 1, b'a',
+                0x12, 36, // scope_metrics
+                    0x0a, 3, 0x0a, 1, b'm', // scope
+                    0x12, 29, // metric
+                        0x0a, 1, b'c',
+                        0x3a, 24, // sum
+                            0x0a, 18, // data point
+                                0x19, 1, 0, 0, 0, 0, 0, 0, 0, // time_unix_nano
+                                0x31, 0x2c, 1, 0, 0, 0, 0, 0, 0, // as_int
+                            0x10, 2, // cumulative
+                            0x18, 1, // monotonic
+        ];
+        assert_eq!(encode_request(&metrics), expected);
+    }
+
+    #[test]
+    fn decodes_partial_success() {
+        let response = [0x0a, 5, 0x08, 2, 0x12, 1, b'x'];
+        assert_eq!(decode_partial_success(&response), Some((2, "x".into())));
+        assert_eq!(decode_partial_success(&[0x0a, 0]), None);
+        assert_eq!(decode_partial_success(&[]), None);
+    }
+}
@@ -0,0 +1,585 @@
+//! Encodes collections in the Prometheus text exposition format, version
+//! 0.0.4, or in the OpenMetrics 1.0 text format.
+//!
+//! Prometheus expects cumulative values, so collect through a cumulative
+//! reader: delta sums and histograms are left out, as are exponential
+//! histograms, which neither format can represent. The resource is exposed as
+//! a `target_info` series and every series carries the `otel_scope_name`
+//! label of the meter that produced it.
+//!
+//! OpenMetrics output adds `# UNIT` lines, `_created` series taken from the
+//! start time of each point and the closing `# EOF`. Data points carry no
+//! exemplars, so none are written.
+
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    fmt::Write,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    common::KeyValue,
+    metric::{Metric, MetricData, ResourceMetrics, Temporality},
+    scope::InstrumentationScope,
+};
+
+/// `Content-Type` of the Prometheus text format.
+pub const TEXT_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+/// `Content-Type` of the OpenMetrics text format.
+pub const OPENMETRICS_CONTENT_TYPE: &str =
+    "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Exposition format written by [`encode`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Prometheus text format 0.0.4.
+    Text,
+    /// OpenMetrics 1.0.
+    OpenMetrics,
+}
+
+impl Format {
+    /// Picks the format for a request's `Accept` header: OpenMetrics when the
+    /// client asks for it, the Prometheus text format otherwise.
+    pub fn negotiate(accept: Option<&str>) -> Format {
+        let accepts_openmetrics = accept.is_some_and(|accept| {
+            accept.split(',').any(|range| {
+                let mut params = range.split(';').map(str::trim);
+                params.next() == Some("application/openmetrics-text")
+                    && !params.any(|param| {
+                        param.strip_prefix("q=").and_then(|q| q.parse::<f64>().ok()) == Some(0.0)
+                    })
+            })
+        });
+        if accepts_openmetrics {
+            Format::OpenMetrics
+        } else {
+            Format::Text
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Format::Text => TEXT_CONTENT_TYPE,
+            Format::OpenMetrics => OPENMETRICS_CONTENT_TYPE,
+        }
+    }
+}
+
+/// Encodes `metrics` in `format`.
+///
+/// Metrics with the same name from different meters are merged into one
+/// family; a metric whose type conflicts with an earlier one of the same name
+/// is dropped.
+pub fn encode(metrics: &ResourceMetrics, format: Format) -> String {
+    let mut out = String::new();
+    if !metrics.resource.is_empty() {
+        // OpenMetrics names info families without the `_info` their samples
+        // carry; the text format has no info type and uses a gauge.
+        let (family, kind) = match format {
+            Format::Text => ("target_info", "gauge"),
+            Format::OpenMetrics => ("target", "info"),
+        };
+        writeln!(out, "# HELP {} Target metadata", family).unwrap();
+        writeln!(out, "# TYPE {} {}", family, kind).unwrap();
+        let attributes: Vec<KeyValue> = metrics.resource.iter().cloned().collect();
+        writeln!(out, "target_info{} 1", labels(&[], &attributes, None)).unwrap();
+    }
+
+    let mut families: BTreeMap<String, Family> = BTreeMap::new();
+    for scope_metrics in &metrics.scope_metrics {
+        let scope = scope_labels(&scope_metrics.scope);
+        for metric in &scope_metrics.metrics {
+            let Some(kind) = family_type(&metric.data) else {
+                continue;
+            };
+            let (name, unit) = family_name(metric, kind);
+            let family = families.entry(name.clone()).or_insert_with(|| Family {
+                help: metric.description.clone(),
+                kind,
+                unit,
+                samples: String::new(),
+            });
+            if family.kind == kind {
+                write_samples(&mut family.samples, &name, &scope, &metric.data, format);
+            }
+        }
+    }
+
+    for (name, family) in families {
+        // The text format names counter families after their samples.
+        let name = match (format, family.kind) {
+            (Format::Text, "counter") => format!("{}_total", name),
+            _ => name,
+        };
+        if !family.help.is_empty() {
+            writeln!(out, "# HELP {} {}", name, escape_help(&family.help, format)).unwrap();
+        }
+        writeln!(out, "# TYPE {} {}", name, family.kind).unwrap();
+        if let (Format::OpenMetrics, Some(unit)) = (format, &family.unit) {
+            writeln!(out, "# UNIT {} {}", name, unit).unwrap();
+        }
+        out.push_str(&family.samples);
+    }
+    if format == Format::OpenMetrics {
+        out.push_str("# EOF\n");
+    }
+    out
+}
+
+struct Family {
+    help: String,
+    kind: &'static str,
+    unit: Option<String>,
+    samples: String,
+}
+
+/// The Prometheus type for `data`, or `None` when it cannot be exposed.
+fn family_type(data: &MetricData) -> Option<&'static str> {
+    match data {
+        MetricData::Sum(sum) if sum.temporality == Temporality::Cumulative => {
+            Some(if sum.is_monotonic { "counter" } else { "gauge" })
+        }
+        MetricData::Gauge(_) => Some("gauge"),
+        MetricData::Histogram(histogram) if histogram.temporality == Temporality::Cumulative => {
+            Some("histogram")
+        }
+        _ => None,
+    }
+}
+
+fn write_samples(
+    out: &mut String,
+    name: &str,
+    scope: &[KeyValue],
+    data: &MetricData,
+    format: Format,
+) {
+    let created = |out: &mut String, labels: &str, start_time: SystemTime| {
+        if format == Format::OpenMetrics {
+            writeln!(
+                out,
+                "{}_created{} {}",
+                name,
+                labels,
+                unix_seconds(start_time)
+            )
+            .unwrap();
+        }
+    };
+    match data {
+        MetricData::Sum(sum) => {
+            let is_counter = sum.is_monotonic;
+            for point in &sum.data_points {
+                let labels = labels(scope, &point.attributes, None);
+                if is_counter {
+                    writeln!(out, "{}_total{} {}", name, labels, point.value).unwrap();
+                    created(out, &labels, point.start_time);
+                } else {
+                    writeln!(out, "{}{} {}", name, labels, point.value).unwrap();
+                }
+            }
+        }
+        MetricData::Gauge(gauge) => {
+            for point in &gauge.data_points {
+                let labels = labels(scope, &point.attributes, None);
+                writeln!(out, "{}{} {}", name, labels, point.value).unwrap();
+            }
+        }
+        MetricData::Histogram(histogram) => {
+            for point in &histogram.data_points {
+                let mut cumulative = 0;
+                for (i, count) in point.bucket_counts.iter().enumerate() {
+                    cumulative += count;
+                    let le = point
+                        .bounds
+                        .get(i)
+                        .map_or_else(|| "+Inf".to_string(), |bound| format_float(*bound));
+                    let labels = labels(scope, &point.attributes, Some(("le", le)));
+                    writeln!(out, "{}_bucket{} {}", name, labels, cumulative).unwrap();
+                }
+                let labels = labels(scope, &point.attributes, None);
+                writeln!(out, "{}_sum{} {}", name, labels, point.sum).unwrap();
+                writeln!(out, "{}_count{} {}", name, labels, point.count).unwrap();
+                created(out, &labels, point.start_time);
+            }
+        }
+        MetricData::ExponentialHistogram(_) => {}
+    }
+}
+
+fn unix_seconds(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    format!(
+        "{}.{:03}",
+        since_epoch.as_secs(),
+        since_epoch.subsec_millis()
+    )
+}
+
+/// Sanitized family name with the unit suffix, and the unit suffix itself
+/// when the name ends with it. Counter families leave out `_total`, which
+/// their samples add.
+fn family_name(metric: &Metric, kind: &str) -> (String, Option<String>) {
+    let mut name = sanitize(&metric.name, true);
+    if kind == "counter" {
+        if let Some(stripped) = name.strip_suffix("_total") {
+            name.truncate(stripped.len());
+        }
+    }
+    let unit = unit_suffix(&metric.unit, kind == "gauge");
+    if let Some(unit) = &unit {
+        if !name.ends_with(&format!("_{}", unit)) {
+            name.push('_');
+            name.push_str(unit);
+        }
+    }
+    (name, unit)
+}
+
+/// Spells out a UCUM unit the way Prometheus metric names do, dropping
+/// `{annotations}`.
+fn unit_suffix(unit: &str, is_gauge: bool) -> Option<String> {
+    let unit = match unit.find('{') {
+        Some(start) => &unit[..start],
+        None => unit,
+    };
+    if unit.is_empty() {
+        return None;
+    }
+    if unit == "1" {
+        return is_gauge.then(|| "ratio".to_string());
+    }
+    let suffix = match unit.split_once('/') {
+        Some((numerator, denominator)) => {
+            let numerator = spell_unit(numerator);
+            let denominator = spell_per_unit(denominator);
+            match (numerator.is_empty(), denominator.is_empty()) {
+                (_, true) => numerator.to_string(),
+                (true, false) => format!("per_{}", denominator),
+                (false, false) => format!("{}_per_{}", numerator, denominator),
+            }
+        }
+        None => spell_unit(unit).to_string(),
+    };
+    let suffix = sanitize(&suffix, false);
+    (!suffix.trim_matches('_').is_empty()).then(|| suffix.trim_matches('_').to_string())
+}
+
+fn spell_unit(unit: &str) -> &str {
+    match unit {
+        "d" => "days",
+        "h" => "hours",
+        "min" => "minutes",
+        "s" => "seconds",
+        "ms" => "milliseconds",
+        "us" => "microseconds",
+        "ns" => "nanoseconds",
+        "By" => "bytes",
+        "KiBy" => "kibibytes",
+        "MiBy" => "mebibytes",
+        "GiBy" => "gibibytes",
+        "KBy" => "kilobytes",
+        "MBy" => "megabytes",
+        "GBy" => "gigabytes",
+        "m" => "meters",
+        "g" => "grams",
+        "V" => "volts",
+        "A" => "amperes",
+        "J" => "joules",
+        "W" => "watts",
+        "Hz" => "hertz",
+        "Cel" => "celsius",
+        "%" => "percent",
+        "mo" => "months",
+        "y" => "years",
+        _ => unit,
+    }
+}
+
+/// Units after a `/` are spelled in the singular.
+fn spell_per_unit(unit: &str) -> &str {
+    match unit {
+        "s" => "second",
+        "m" => "minute",
+        "h" => "hour",
+        "d" => "day",
+        "w" => "week",
+        "mo" => "month",
+        "y" => "year",
+        _ => spell_unit(unit),
+    }
+}
+
+/// Replaces every character Prometheus does not allow with `_`. Colons are
+/// only allowed in metric names.
+fn sanitize(name: &str, allow_colon: bool) -> String {
+    let mut sanitized: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || (allow_colon && c == ':') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if sanitized.starts_with(|c: char| c.is_ascii_digit()) || sanitized.is_empty() {
+        sanitized.insert(0, '_');
+    }
+    sanitized
+}
+
+fn scope_labels(scope: &InstrumentationScope) -> Vec<KeyValue> {
+    let mut labels = vec![KeyValue::new("otel_scope_name", scope.name().to_string())];
+    if let Some(version) = scope.version() {
+        labels.push(KeyValue::new("otel_scope_version", version.to_string()));
+    }
+    labels
+}
+
+/// Renders `{key="value",...}`, or nothing when there are no labels.
+///
+/// Attribute keys that sanitize to the same label name have their values
+/// joined with `;`.
+fn labels(scope: &[KeyValue], attributes: &[KeyValue], extra: Option<(&str, String)>) -> String {
+    let mut merged: BTreeMap<String, String> = BTreeMap::new();
+    for kv in attributes {
+        let key = sanitize(kv.key.as_str(), false);
+        let value = kv.value.as_str();
+        merged
+            .entry(key)
+            .and_modify(|existing| {
+                existing.push(';');
+                existing.push_str(&value);
+            })
+            .or_insert_with(|| value.into_owned());
+    }
+    for kv in scope {
+        merged.insert(kv.key.as_str().to_string(), kv.value.as_str().into_owned());
+    }
+    if let Some((key, value)) = extra {
+        merged.insert(key.to_string(), value);
+    }
+    if merged.is_empty() {
+        return String::new();
+    }
+
+    let mut out = String::from("{");
+    for (i, (key, value)) in merged.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        write!(out, "{}=\"{}\"", key, escape_label_value(value)).unwrap();
+    }
+    out.push('}');
+    out
+}
+
+fn escape_label_value(value: &str) -> Cow<'_, str> {
+    if !value.contains(['\\', '"', '\n']) {
+        return Cow::Borrowed(value);
+    }
+    Cow::Owned(
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n"),
+    )
+}
+
+/// OpenMetrics also escapes `"` in HELP text; the text format does not.
+fn escape_help(help: &str, format: Format) -> String {
+    let help = help.replace('\\', "\\\\").replace('\n', "\\n");
+    match format {
+        Format::Text => help,
+        Format::OpenMetrics => help.replace('"', "\\\""),
+    }
+}
+
+fn format_float(value: f64) -> String {
+    if value == f64::INFINITY {
+        "+Inf".to_string()
+    } else if value == f64::NEG_INFINITY {
+        "-Inf".to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metric::{DataPoint, Histogram, HistogramDataPoint, ScopeMetrics, Sum};
+    use crate::resource::Resource;
+    use std::time::SystemTime;
+
+    fn metric(name: &str, unit: &str, data: MetricData) -> Metric {
+        Metric::new(name.into(), "Handled requests".into(), unit.into(), data)
+    }
+
+    fn collection(metrics: Vec<Metric>) -> ResourceMetrics {
+        ResourceMetrics {
+            resource: Resource::new([KeyValue::new("service.name", "checkout")]),
+            scope_metrics: vec![ScopeMetrics {
+                scope: InstrumentationScope::new("http").with_version("1.0"),
+                metrics,
+            }],
+        }
+    }
+
+    fn point(attributes: Vec<KeyValue>, value: u64) -> DataPoint {
+        DataPoint {
+            attributes,
+            start_time: SystemTime::UNIX_EPOCH,
+            time: SystemTime::UNIX_EPOCH,
+            value,
+        }
+    }
+
+    #[test]
+    fn encodes_counters() {
+        let sum = Sum {
+            data_points: vec![point(
+                vec![
+                    KeyValue::new("http.route", "/a\"b\\\n"),
+                    KeyValue::new("http_route", "x"),
+                ],
+                7,
+            )],
+            temporality: Temporality::Cumulative,
+            is_monotonic: true,
+        };
+        let encoded = encode(
+            &collection(vec![metric(
+                "http.server.duration",
+                "ms",
+                MetricData::Sum(sum),
+            )]),
+            Format::Text,
+        );
+        assert_eq!(
+            encoded,
+            "# HELP target_info Target metadata\n\
+             # TYPE target_info gauge\n\
+             target_info{service_name=\"checkout\"} 1\n\
+             # HELP http_server_duration_milliseconds_total Handled requests\n\
+             # TYPE http_server_duration_milliseconds_total counter\n\
+             http_server_duration_milliseconds_total{http_route=\"/a\\\"b\\\\\\n;x\",\
+             otel_scope_name=\"http\",otel_scope_version=\"1.0\"} 7\n"
+        );
+    }
+
+    #[test]
+    fn encodes_histograms_and_skips_deltas() {
+        let histogram = Histogram {
+            data_points: vec![HistogramDataPoint {
+                attributes: vec![],
+                start_time: SystemTime::UNIX_EPOCH,
+                time: SystemTime::UNIX_EPOCH,
+                count: 4,
+                sum: 30,
+                min: Some(1),
+                max: Some(20),
+                bounds: vec![0.5, 10.0],
+                bucket_counts: vec![0, 3, 1],
+            }],
+            temporality: Temporality::Cumulative,
+        };
+        let delta = Sum {
+            data_points: vec![point(vec![], 1)],
+            temporality: Temporality::Delta,
+            is_monotonic: true,
+        };
+        let encoded = encode(
+            &collection(vec![
+                metric("latency", "s", MetricData::Histogram(histogram)),
+                metric("requests", "", MetricData::Sum(delta)),
+            ]),
+            Format::Text,
+        );
+        let series: Vec<&str> = encoded
+            .lines()
+            .filter(|line| line.starts_with("latency"))
+            .collect();
+        assert_eq!(
+            series,
+            vec![
+                "latency_seconds_bucket{le=\"0.5\",otel_scope_name=\"http\",otel_scope_version=\"1.0\"} 0",
+                "latency_seconds_bucket{le=\"10\",otel_scope_name=\"http\",otel_scope_version=\"1.0\"} 3",
+                "latency_seconds_bucket{le=\"+Inf\",otel_scope_name=\"http\",otel_scope_version=\"1.0\"} 4",
+                "latency_seconds_sum{otel_scope_name=\"http\",otel_scope_version=\"1.0\"} 30",
+                "latency_seconds_count{otel_scope_name=\"http\",otel_scope_version=\"1.0\"} 4",
+            ]
+        );
+        assert!(!encoded.contains("requests_total"));
+    }
+
+    #[test]
+    fn names_and_units() {
+        assert_eq!(sanitize("1st.metric-name:x", true), "_1st_metric_name:x");
+        assert_eq!(sanitize("a:b", false), "a_b");
+        assert_eq!(
+            unit_suffix("By/s", false).as_deref(),
+            Some("bytes_per_second")
+        );
+        assert_eq!(unit_suffix("{request}", false), None);
+        assert_eq!(unit_suffix("1", true).as_deref(), Some("ratio"));
+        assert_eq!(unit_suffix("1", false), None);
+        let counter = metric(
+            "requests_total",
+            "{request}",
+            MetricData::Sum(Sum {
+                data_points: vec![],
+                temporality: Temporality::Cumulative,
+                is_monotonic: true,
+            }),
+        );
+        assert_eq!(family_name(&counter, "counter"), ("requests".into(), None));
+    }
+
+    #[test]
+    fn encodes_openmetrics() {
+        let sum = Sum {
+            data_points: vec![DataPoint {
+                start_time: SystemTime::UNIX_EPOCH + std::time::Duration::from_millis(1_500),
+                ..point(vec![], 3)
+            }],
+            temporality: Temporality::Cumulative,
+            is_monotonic: true,
+        };
+        let io = Metric {
+            description: "Handled \"io\"".into(),
+            ..metric("io", "By", MetricData::Sum(sum))
+        };
+        let encoded = encode(&collection(vec![io]), Format::OpenMetrics);
+        let scope = "{otel_scope_name=\"http\",otel_scope_version=\"1.0\"}";
+        assert_eq!(
+            encoded,
+            format!(
+                "# HELP target Target metadata\n\
+                 # TYPE target info\n\
+                 target_info{{service_name=\"checkout\"}} 1\n\
+                 # HELP io_bytes Handled \\\"io\\\"\n\
+                 # TYPE io_bytes counter\n\
+                 # UNIT io_bytes bytes\n\
+                 io_bytes_total{scope} 3\n\
+                 io_bytes_created{scope} 1.500\n\
+                 # EOF\n"
+            )
+        );
+    }
+
+    #[test]
+    fn negotiates_format() {
+        let prometheus =
+            "application/openmetrics-text;version=1.0.0,text/plain;version=0.0.4;q=0.5";
+        assert_eq!(Format::negotiate(Some(prometheus)), Format::OpenMetrics);
+        assert_eq!(
+            Format::negotiate(Some("application/openmetrics-text; q=0, */*")),
+            Format::Text
+        );
+        assert_eq!(Format::negotiate(None), Format::Text);
+    }
+}
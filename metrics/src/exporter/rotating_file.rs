@@ -0,0 +1,105 @@
+//! An append-only file for the file exporters, rotated once it grows too
+//! large or too old.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+/// Before a write would take the file past `max_size`, or once the file has
+/// been open for `max_age`, the file is rotated: `metrics.csv` becomes
+/// `metrics.csv.1`, older backups move up by one and the oldest beyond
+/// `max_backups` is deleted. With no backups the file is truncated instead.
+#[derive(Debug)]
+pub(crate) struct RotatingFile {
+    path: PathBuf,
+    max_size: u64,
+    max_age: Option<Duration>,
+    max_backups: usize,
+    /// Written at the start of every new or empty file.
+    header: Vec<u8>,
+    file: File,
+    size: u64,
+    opened: Instant,
+}
+
+impl RotatingFile {
+    /// Opens `path` for appending, creating it if needed.
+    pub(crate) fn open(
+        path: &Path,
+        max_size: u64,
+        max_age: Option<Duration>,
+        max_backups: usize,
+        header: Vec<u8>,
+    ) -> io::Result<RotatingFile> {
+        let (file, size) = open(path)?;
+        Ok(RotatingFile {
+            path: path.to_path_buf(),
+            max_size,
+            max_age,
+            max_backups,
+            header,
+            file,
+            size,
+            opened: Instant::now(),
+        })
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends `data`, rotating first if needed. Data larger than the size
+    /// limit still gets a file of its own.
+    pub(crate) fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        let has_data = self.size > self.header.len() as u64;
+        let full = self.size + data.len() as u64 > self.max_size;
+        let expired = self
+            .max_age
+            .is_some_and(|max_age| self.opened.elapsed() >= max_age);
+        if has_data && (full || expired) {
+            self.rotate()?;
+            (self.file, self.size) = open(&self.path)?;
+            self.opened = Instant::now();
+        }
+        if self.size == 0 && !self.header.is_empty() {
+            self.file.write_all(&self.header)?;
+            self.size += self.header.len() as u64;
+        }
+        self.file.write_all(data)?;
+        self.size += data.len() as u64;
+        Ok(())
+    }
+
+    pub(crate) fn sync(&self) -> io::Result<()> {
+        self.file.sync_all()
+    }
+
+    fn rotate(&self) -> io::Result<()> {
+        if self.max_backups == 0 {
+            return fs::remove_file(&self.path);
+        }
+        let _ = fs::remove_file(self.backup(self.max_backups));
+        for index in (1..self.max_backups).rev() {
+            let from = self.backup(index);
+            if from.exists() {
+                fs::rename(from, self.backup(index + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.backup(1))
+    }
+
+    pub(crate) fn backup(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", index));
+        path.into()
+    }
+}
+
+fn open(path: &Path) -> io::Result<(File, u64)> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let size = file.metadata()?.len();
+    Ok((file, size))
+}
@@ -0,0 +1,110 @@
+//! Collections flattened into one entry per data point, for the exporters
+//! that print rows rather than protocol messages.
+
+use std::time::SystemTime;
+
+use crate::{
+    common::{KeyValue, Value},
+    metric::{Metric, MetricData, ResourceMetrics},
+};
+
+pub(crate) struct Series<'a> {
+    pub(crate) meter: &'a str,
+    pub(crate) metric: &'a Metric,
+    pub(crate) attributes: &'a [KeyValue],
+    pub(crate) time: SystemTime,
+    pub(crate) value: SeriesValue,
+}
+
+pub(crate) enum SeriesValue {
+    Number(u64),
+    Histogram {
+        count: u64,
+        sum: u64,
+        min: Option<u64>,
+        max: Option<u64>,
+    },
+}
+
+/// Every data point in `metrics`, in collection order.
+pub(crate) fn series(metrics: &ResourceMetrics) -> Vec<Series<'_>> {
+    let mut series = vec![];
+    for scope_metrics in &metrics.scope_metrics {
+        let meter = scope_metrics.scope.name();
+        for metric in &scope_metrics.metrics {
+            let mut push = |attributes, time, value| {
+                series.push(Series {
+                    meter,
+                    metric,
+                    attributes,
+                    time,
+                    value,
+                })
+            };
+            match &metric.data {
+                MetricData::Sum(sum) => {
+                    for point in &sum.data_points {
+                        let value = SeriesValue::Number(point.value);
+                        push(&point.attributes, point.time, value);
+                    }
+                }
+                MetricData::Gauge(gauge) => {
+                    for point in &gauge.data_points {
+                        let value = SeriesValue::Number(point.value);
+                        push(&point.attributes, point.time, value);
+                    }
+                }
+                MetricData::Histogram(histogram) => {
+                    for point in &histogram.data_points {
+                        let value = SeriesValue::Histogram {
+                            count: point.count,
+                            sum: point.sum,
+                            min: point.min,
+                            max: point.max,
+                        };
+                        push(&point.attributes, point.time, value);
+                    }
+                }
+                MetricData::ExponentialHistogram(histogram) => {
+                    for point in &histogram.data_points {
+                        let value = SeriesValue::Histogram {
+                            count: point.count,
+                            sum: point.sum,
+                            min: point.min,
+                            max: point.max,
+                        };
+                        push(&point.attributes, point.time, value);
+                    }
+                }
+            }
+        }
+    }
+    series
+}
+
+/// Attributes as `k=v` pairs separated by `separator`.
+pub(crate) fn format_attributes(attributes: &[KeyValue], separator: &str) -> String {
+    attributes
+        .iter()
+        .map(|kv| format!("{}={}", kv.key.as_str(), kv.value))
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+/// Attributes as a JSON object, keeping booleans and numbers typed.
+pub(crate) fn attributes_json(
+    attributes: &[KeyValue],
+) -> serde_json::Map<String, serde_json::Value> {
+    attributes
+        .iter()
+        .map(|kv| {
+            let value = match &kv.value {
+                Value::Bool(value) => serde_json::Value::from(*value),
+                Value::I64(value) => serde_json::Value::from(*value),
+                Value::F64(value) => serde_json::Value::from(*value),
+                value => serde_json::Value::from(value.to_string()),
+            };
+            (kv.key.as_str().to_string(), value)
+        })
+        .collect()
+}
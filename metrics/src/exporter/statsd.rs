@@ -0,0 +1,435 @@
+use std::{
+    io,
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    time::Duration,
+};
+
+#[cfg(unix)]
+use std::{os::unix::net::UnixDatagram, path::PathBuf};
+
+use crate::{
+    common::KeyValue,
+    error::{MetricsError, MetricsResult},
+    instrument::InstrumentKind,
+    metric::{MetricData, ResourceMetrics, Temporality},
+};
+
+use super::{Packets, PushMetricExporter};
+
+/// A local StatsD agent on its conventional port.
+pub const DEFAULT_STATSD_ADDRESS: &str = "127.0.0.1:8125";
+
+/// Fits a datagram into a 1500 byte Ethernet MTU after IP and UDP headers.
+const DEFAULT_MAX_PACKET_SIZE: usize = 1432;
+
+/// StatsD type used for histogram data points.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum HistogramType {
+    /// `|h`, aggregated by the agent.
+    #[default]
+    Histogram,
+    /// `|d`, the DogStatsD distribution, aggregated server side.
+    Distribution,
+}
+
+/// Sends every collected series to a StatsD agent as DogStatsD lines.
+///
+/// Monotonic sums become counters (`|c`) and other sums and gauges become
+/// gauges (`|g`). StatsD histograms expect raw samples, so each non-empty
+/// bucket is sent as one sample at its upper bound, capped at the point's
+/// maximum, with a sample rate of `1/count`. Without a recorded maximum, the
+/// overflow bucket is sent at the last bound, or at the mean when there are
+/// no bounds. Attributes become `#key:value` tags. Lines are packed into
+/// datagrams of at most the configured size.
+///
+/// The exporter asks for delta temporality, which is what StatsD counters
+/// expect.
+///
+/// ```no_run
+/// use metrics::exporter::StatsdExporter;
+/// use metrics::periodic_reader::PeriodicReader;
+///
+/// let exporter = StatsdExporter::builder()
+///     .with_prefix("checkout.")
+///     .build()
+///     .unwrap();
+/// let reader = PeriodicReader::new(exporter);
+/// ```
+#[derive(Debug)]
+pub struct StatsdExporter {
+    transport: Transport,
+    prefix: String,
+    max_packet_size: usize,
+    histogram_type: HistogramType,
+}
+
+/// Configures a [`StatsdExporter`].
+#[derive(Debug)]
+pub struct StatsdExporterBuilder {
+    target: Target,
+    prefix: String,
+    max_packet_size: usize,
+    histogram_type: HistogramType,
+}
+
+#[derive(Debug)]
+enum Target {
+    Udp(String),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+#[derive(Debug)]
+enum Transport {
+    Udp(UdpSocket, SocketAddr),
+    #[cfg(unix)]
+    Unix(UnixDatagram, PathBuf),
+}
+
+impl StatsdExporter {
+    pub fn builder() -> StatsdExporterBuilder {
+        StatsdExporterBuilder {
+            target: Target::Udp(DEFAULT_STATSD_ADDRESS.to_string()),
+            prefix: String::new(),
+            max_packet_size: DEFAULT_MAX_PACKET_SIZE,
+            histogram_type: HistogramType::default(),
+        }
+    }
+}
+
+impl StatsdExporterBuilder {
+    /// Sends datagrams over UDP to `addr`, [`DEFAULT_STATSD_ADDRESS`] by
+    /// default.
+    pub fn with_udp(mut self, addr: &str) -> Self {
+        self.target = Target::Udp(addr.to_string());
+        self
+    }
+
+    /// Sends datagrams to the Unix domain socket at `path`, as the DogStatsD
+    /// agent accepts.
+    #[cfg(unix)]
+    pub fn with_unix_socket(mut self, path: impl Into<PathBuf>) -> Self {
+        self.target = Target::Unix(path.into());
+        self
+    }
+
+    /// Prepended to every metric name.
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    /// Largest datagram sent, 1432 bytes by default. A line that does not fit
+    /// on its own is sent in a datagram of its own.
+    pub fn with_max_packet_size(mut self, bytes: usize) -> Self {
+        self.max_packet_size = bytes;
+        self
+    }
+
+    pub fn with_histogram_type(mut self, histogram_type: HistogramType) -> Self {
+        self.histogram_type = histogram_type;
+        self
+    }
+
+    pub fn build(self) -> MetricsResult<StatsdExporter> {
+        let invalid = |err: io::Error| MetricsError::InvalidConfig(format!("statsd: {}", err));
+        let transport = match self.target {
+            Target::Udp(addr) => {
+                let addr = addr
+                    .to_socket_addrs()
+                    .map_err(invalid)?
+                    .next()
+                    .ok_or_else(|| {
+                        MetricsError::InvalidConfig(format!("statsd: {} did not resolve", addr))
+                    })?;
+                let local = match addr {
+                    SocketAddr::V4(_) => "0.0.0.0:0",
+                    SocketAddr::V6(_) => "[::]:0",
+                };
+                Transport::Udp(UdpSocket::bind(local).map_err(invalid)?, addr)
+            }
+            #[cfg(unix)]
+            Target::Unix(path) => Transport::Unix(UnixDatagram::unbound().map_err(invalid)?, path),
+        };
+        Ok(StatsdExporter {
+            transport,
+            prefix: self.prefix,
+            max_packet_size: self.max_packet_size,
+            histogram_type: self.histogram_type,
+        })
+    }
+}
+
+impl PushMetricExporter for StatsdExporter {
+    fn export(&self, metrics: &ResourceMetrics, _timeout: Duration) -> MetricsResult<()> {
+        let mut packets = Packets::new(self.max_packet_size);
+        for metric in metrics.metrics() {
+            let name = format!("{}{}", self.prefix, sanitize(&metric.name));
+            match &metric.data {
+                MetricData::Sum(sum) => {
+                    let kind = if sum.is_monotonic { "c" } else { "g" };
+                    for point in &sum.data_points {
+                        packets.push(line(&name, point.value, kind, None, &point.attributes));
+                    }
+                }
+                MetricData::Gauge(gauge) => {
+                    for point in &gauge.data_points {
+                        packets.push(line(&name, point.value, "g", None, &point.attributes));
+                    }
+                }
+                MetricData::Histogram(histogram) => {
+                    for point in &histogram.data_points {
+                        // The overflow bucket has no upper bound: use the
+                        // maximum, else the last bound, else the mean.
+                        let overflow = point
+                            .max
+                            .map(|max| max as f64)
+                            .or(point.bounds.last().copied())
+                            .unwrap_or(point.sum as f64 / point.count.max(1) as f64);
+                        for (index, count) in point.bucket_counts.iter().enumerate() {
+                            let bound = point.bounds.get(index).copied().unwrap_or(overflow);
+                            let value = cap(bound, point.max);
+                            packets.push(self.sample(&name, value, *count, &point.attributes));
+                        }
+                    }
+                }
+                MetricData::ExponentialHistogram(histogram) => {
+                    for point in &histogram.data_points {
+                        packets.push(self.sample(&name, 0.0, point.zero_count, &point.attributes));
+                        let base_exponent = (-(point.scale as f64)).exp2();
+                        for (index, count) in point.positive_bucket_counts.iter().enumerate() {
+                            let bucket = point.positive_offset as f64 + index as f64;
+                            let bound = ((bucket + 1.0) * base_exponent).exp2();
+                            let value = cap(bound, point.max);
+                            packets.push(self.sample(&name, value, *count, &point.attributes));
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut result = Ok(());
+        for packet in packets.finish() {
+            if let Err(err) = self.send(packet.as_bytes()) {
+                result = Err(MetricsError::Export(format!("statsd: {}", err)));
+            }
+        }
+        result
+    }
+
+    fn temporality(&self, _kind: InstrumentKind) -> Temporality {
+        Temporality::Delta
+    }
+}
+
+impl StatsdExporter {
+    /// `count` samples of `value`, or no line for an empty bucket or a value
+    /// StatsD cannot represent.
+    fn sample(&self, name: &str, value: f64, count: u64, attributes: &[KeyValue]) -> String {
+        if count == 0 || !value.is_finite() {
+            return String::new();
+        }
+        let kind = match self.histogram_type {
+            HistogramType::Histogram => "h",
+            HistogramType::Distribution => "d",
+        };
+        let rate = (count > 1).then(|| 1.0 / count as f64);
+        line(name, value, kind, rate, attributes)
+    }
+
+    fn send(&self, packet: &[u8]) -> io::Result<()> {
+        match &self.transport {
+            Transport::Udp(socket, addr) => socket.send_to(packet, addr).map(drop),
+            #[cfg(unix)]
+            Transport::Unix(socket, path) => socket.send_to(packet, path).map(drop),
+        }
+    }
+}
+
+fn line(
+    name: &str,
+    value: impl std::fmt::Display,
+    kind: &str,
+    rate: Option<f64>,
+    attributes: &[KeyValue],
+) -> String {
+    let mut line = format!("{}:{}|{}", name, value, kind);
+    if let Some(rate) = rate {
+        line.push_str(&format!("|@{}", rate));
+    }
+    for (i, kv) in attributes.iter().enumerate() {
+        line.push_str(if i == 0 { "|#" } else { "," });
+        line.push_str(&sanitize(kv.key.as_str()));
+        line.push(':');
+        line.push_str(&sanitize(&kv.value.to_string()));
+    }
+    line
+}
+
+fn cap(bound: f64, max: Option<u64>) -> f64 {
+    match max {
+        Some(max) => bound.min(max as f64),
+        None => bound,
+    }
+}
+
+/// Replaces the characters that delimit names, values and tags.
+fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            ':' | '|' | '@' | '#' | ',' | '\n' => '_',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metric::{DataPoint, Histogram, HistogramDataPoint, Metric, ScopeMetrics, Sum};
+    use crate::{
+        aggregation::Aggregation, meter_provider::MeterProvider, periodic_reader::PeriodicReader,
+        resource::Resource, scope::InstrumentationScope, view::View,
+    };
+    use std::time::SystemTime;
+
+    fn metrics() -> ResourceMetrics {
+        let now = SystemTime::now();
+        let point = |value, attributes| DataPoint {
+            attributes,
+            start_time: now,
+            time: now,
+            value,
+        };
+        ResourceMetrics {
+            resource: Resource::empty(),
+            scope_metrics: vec![ScopeMetrics {
+                scope: InstrumentationScope::new("http"),
+                metrics: vec![
+                    Metric::new(
+                        "requests".into(),
+                        String::new(),
+                        String::new(),
+                        MetricData::Sum(Sum {
+                            data_points: vec![
+                                point(3, vec![KeyValue::new("route", "/a:b")]),
+                                point(1, vec![KeyValue::new("route", "/c")]),
+                            ],
+                            temporality: Temporality::Delta,
+                            is_monotonic: true,
+                        }),
+                    ),
+                    Metric::new(
+                        "latency".into(),
+                        String::new(),
+                        "ms".into(),
+                        MetricData::Histogram(Histogram {
+                            data_points: vec![HistogramDataPoint {
+                                attributes: vec![],
+                                start_time: now,
+                                time: now,
+                                count: 5,
+                                sum: 130,
+                                min: Some(5),
+                                max: Some(60),
+                                bounds: vec![10.0, 50.0],
+                                bucket_counts: vec![4, 0, 1],
+                            }],
+                            temporality: Temporality::Delta,
+                        }),
+                    ),
+                ],
+            }],
+        }
+    }
+
+    #[test]
+    fn packs_lines_into_udp_datagrams() {
+        let agent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        agent
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let exporter = StatsdExporter::builder()
+            .with_udp(&agent.local_addr().unwrap().to_string())
+            .with_prefix("app.")
+            .with_max_packet_size(48)
+            .with_histogram_type(HistogramType::Distribution)
+            .build()
+            .unwrap();
+        exporter.export(&metrics(), Duration::from_secs(1)).unwrap();
+
+        let mut packets = vec![];
+        let mut buf = [0; 1500];
+        for _ in 0..3 {
+            let len = agent.recv(&mut buf).unwrap();
+            packets.push(String::from_utf8(buf[..len].to_vec()).unwrap());
+        }
+        assert_eq!(
+            packets,
+            [
+                "app.requests:3|c|#route:/a_b",
+                "app.requests:1|c|#route:/c",
+                "app.latency:10|d|@0.25\napp.latency:60|d",
+            ]
+        );
+    }
+
+    #[test]
+    fn sends_finite_overflow_samples_without_max() {
+        let agent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        agent
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let exporter = StatsdExporter::builder()
+            .with_udp(&agent.local_addr().unwrap().to_string())
+            .build()
+            .unwrap();
+        let histogram = |boundaries| Aggregation::ExplicitBucketHistogram {
+            boundaries,
+            record_min_max: false,
+        };
+        let provider = MeterProvider::builder()
+            .with_reader(PeriodicReader::new(exporter))
+            .with_view(View::new("latency").with_aggregation(histogram(vec![10.0])))
+            .with_view(View::new("size").with_aggregation(histogram(vec![])))
+            .build()
+            .unwrap();
+        let meter = provider.get_meter("http");
+        let latency = meter.create_counter("latency");
+        latency.add(7, &[]);
+        latency.add(500, &[]);
+        let size = meter.create_counter("size");
+        size.add(30, &[]);
+        size.add(50, &[]);
+        provider.force_flush().unwrap();
+
+        let mut buf = [0; 1500];
+        let len = agent.recv(&mut buf).unwrap();
+        let mut lines: Vec<_> = std::str::from_utf8(&buf[..len]).unwrap().lines().collect();
+        lines.sort();
+        assert_eq!(lines, ["latency:10|h", "latency:10|h", "size:40|h|@0.5"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sends_over_unix_socket() {
+        let dir = std::env::temp_dir().join(format!("metrics-statsd-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("dsd.socket");
+        let agent = UnixDatagram::bind(&path).unwrap();
+        let exporter = StatsdExporter::builder()
+            .with_unix_socket(&path)
+            .build()
+            .unwrap();
+        exporter.export(&metrics(), Duration::from_secs(1)).unwrap();
+
+        let mut buf = [0; 1500];
+        let len = agent.recv(&mut buf).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buf[..len]).unwrap(),
+            "requests:3|c|#route:/a_b\nrequests:1|c|#route:/c\nlatency:10|h|@0.25\nlatency:60|h"
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
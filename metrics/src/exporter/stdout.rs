@@ -0,0 +1,311 @@
+use std::{
+    fmt,
+    io::{self, Write},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use serde_json::json;
+
+use crate::{
+    error::{MetricsError, MetricsResult},
+    instrument::InstrumentKind,
+    metric::{ResourceMetrics, Temporality},
+};
+
+use super::{
+    series::{self, Series, SeriesValue},
+    PushMetricExporter,
+};
+
+/// How the [`StdoutExporter`] prints a collection.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ConsoleFormat {
+    /// An aligned table, one row per series.
+    #[default]
+    Table,
+    /// One JSON object per series and line.
+    Json,
+}
+
+/// Prints every exported collection to stdout, or to any writer.
+///
+/// Series are sorted by meter, instrument and attributes. In the table
+/// format, each collection starts with the resource and has one row per
+/// series: meter, instrument, unit, attributes as `k=v` and the value, or
+/// count, sum, min and max for histograms.
+///
+/// ```text
+/// resource: service.name=checkout
+/// METER  INSTRUMENT  UNIT  ATTRIBUTES  VALUE
+/// http   latency     ms                count=5 sum=130 min=5 max=60
+/// http   requests          route=/a    3
+/// ```
+#[derive(Clone)]
+pub struct StdoutExporter {
+    temporality: Temporality,
+    format: ConsoleFormat,
+    writer: Arc<Mutex<dyn Write + Send>>,
+}
+
+impl Default for StdoutExporter {
+    fn default() -> Self {
+        StdoutExporter::new(Temporality::Delta)
+    }
+}
+
+impl fmt::Debug for StdoutExporter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StdoutExporter")
+            .field("temporality", &self.temporality)
+            .field("format", &self.format)
+            .finish_non_exhaustive()
+    }
+}
+
+impl StdoutExporter {
+    pub fn new(temporality: Temporality) -> StdoutExporter {
+        StdoutExporter {
+            temporality,
+            format: ConsoleFormat::default(),
+            writer: Arc::new(Mutex::new(io::stdout())),
+        }
+    }
+
+    pub fn with_format(mut self, format: ConsoleFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Writes to `writer` instead of stdout.
+    pub fn with_writer(mut self, writer: impl Write + Send + 'static) -> Self {
+        self.writer = Arc::new(Mutex::new(writer));
+        self
+    }
+
+    fn render(&self, metrics: &ResourceMetrics) -> String {
+        let mut series = series::series(metrics);
+        let attributes = |series: &Series| series::format_attributes(series.attributes, ",");
+        series.sort_by_cached_key(|series| {
+            (
+                series.meter,
+                series.metric.name.as_str(),
+                attributes(series),
+            )
+        });
+        match self.format {
+            ConsoleFormat::Table => {
+                let resource: Vec<_> = metrics.resource.iter().cloned().collect();
+                let mut rows = vec![[
+                    "METER".to_string(),
+                    "INSTRUMENT".to_string(),
+                    "UNIT".to_string(),
+                    "ATTRIBUTES".to_string(),
+                    "VALUE".to_string(),
+                ]];
+                rows.extend(series.iter().map(|series| {
+                    [
+                        series.meter.to_string(),
+                        series.metric.name.clone(),
+                        series.metric.unit.clone(),
+                        attributes(series),
+                        match series.value {
+                            SeriesValue::Number(value) => value.to_string(),
+                            SeriesValue::Histogram {
+                                count,
+                                sum,
+                                min,
+                                max,
+                            } => {
+                                let mut summary = format!("count={} sum={}", count, sum);
+                                if let (Some(min), Some(max)) = (min, max) {
+                                    summary.push_str(&format!(" min={} max={}", min, max));
+                                }
+                                summary
+                            }
+                        },
+                    ]
+                }));
+                let mut widths = [0; 5];
+                for row in &rows {
+                    for (width, cell) in widths.iter_mut().zip(row) {
+                        *width = (*width).max(cell.chars().count());
+                    }
+                }
+                let mut out = format!("resource: {}\n", series::format_attributes(&resource, ", "));
+                for row in &rows {
+                    let mut line = String::new();
+                    for (i, cell) in row.iter().enumerate() {
+                        if i + 1 == row.len() {
+                            line.push_str(cell);
+                        } else {
+                            line.push_str(&format!("{:width$}  ", cell, width = widths[i]));
+                        }
+                    }
+                    out.push_str(line.trim_end());
+                    out.push('\n');
+                }
+                out
+            }
+            ConsoleFormat::Json => {
+                let mut out = String::new();
+                for series in &series {
+                    let mut row = json!({
+                        "meter": series.meter,
+                        "instrument": series.metric.name,
+                        "unit": series.metric.unit,
+                        "attributes": series::attributes_json(series.attributes),
+                    });
+                    match series.value {
+                        SeriesValue::Number(value) => row["value"] = json!(value),
+                        SeriesValue::Histogram {
+                            count,
+                            sum,
+                            min,
+                            max,
+                        } => {
+                            row["count"] = json!(count);
+                            row["sum"] = json!(sum);
+                            row["min"] = json!(min);
+                            row["max"] = json!(max);
+                        }
+                    }
+                    out.push_str(&row.to_string());
+                    out.push('\n');
+                }
+                out
+            }
+        }
+    }
+}
+
+impl PushMetricExporter for StdoutExporter {
+    fn export(&self, metrics: &ResourceMetrics, _timeout: Duration) -> MetricsResult<()> {
+        let output = self.render(metrics);
+        let mut writer = self.writer.lock().unwrap();
+        writer
+            .write_all(output.as_bytes())
+            .and_then(|_| writer.flush())
+            .map_err(|err| MetricsError::Export(err.to_string()))
+    }
+
+    fn temporality(&self, _kind: InstrumentKind) -> Temporality {
+        self.temporality
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        common::KeyValue,
+        metric::{DataPoint, Histogram, HistogramDataPoint, Metric, MetricData, ScopeMetrics, Sum},
+        resource::Resource,
+        scope::InstrumentationScope,
+    };
+    use std::time::SystemTime;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn metrics() -> ResourceMetrics {
+        let now = SystemTime::now();
+        let point = |value, attributes| DataPoint {
+            attributes,
+            start_time: now,
+            time: now,
+            value,
+        };
+        ResourceMetrics {
+            resource: Resource::new([KeyValue::new("service.name", "checkout")]),
+            scope_metrics: vec![ScopeMetrics {
+                scope: InstrumentationScope::new("http"),
+                metrics: vec![
+                    Metric::new(
+                        "requests".into(),
+                        String::new(),
+                        String::new(),
+                        MetricData::Sum(Sum {
+                            data_points: vec![
+                                point(12, vec![KeyValue::new("route", "/b")]),
+                                point(3, vec![KeyValue::new("route", "/a")]),
+                            ],
+                            temporality: Temporality::Delta,
+                            is_monotonic: true,
+                        }),
+                    ),
+                    Metric::new(
+                        "latency".into(),
+                        String::new(),
+                        "ms".into(),
+                        MetricData::Histogram(Histogram {
+                            data_points: vec![HistogramDataPoint {
+                                attributes: vec![],
+                                start_time: now,
+                                time: now,
+                                count: 5,
+                                sum: 130,
+                                min: Some(5),
+                                max: Some(60),
+                                bounds: vec![10.0],
+                                bucket_counts: vec![4, 1],
+                            }],
+                            temporality: Temporality::Delta,
+                        }),
+                    ),
+                ],
+            }],
+        }
+    }
+
+    #[test]
+    fn prints_sorted_table() {
+        let buffer = Buffer::default();
+        let exporter = StdoutExporter::default().with_writer(buffer.clone());
+        exporter.export(&metrics(), Duration::from_secs(1)).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap(),
+            "resource: service.name=checkout\n\
+             METER  INSTRUMENT  UNIT  ATTRIBUTES  VALUE\n\
+             http   latency     ms                count=5 sum=130 min=5 max=60\n\
+             http   requests          route=/a    3\n\
+             http   requests          route=/b    12\n"
+        );
+    }
+
+    #[test]
+    fn prints_json_lines() {
+        let buffer = Buffer::default();
+        let exporter = StdoutExporter::default()
+            .with_format(ConsoleFormat::Json)
+            .with_writer(buffer.clone());
+        exporter.export(&metrics(), Duration::from_secs(1)).unwrap();
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let rows: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0]["count"], 5);
+        assert_eq!(
+            rows[1],
+            json!({
+                "meter": "http",
+                "instrument": "requests",
+                "unit": "",
+                "attributes": { "route": "/a" },
+                "value": 3,
+            })
+        );
+    }
+}
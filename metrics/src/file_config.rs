@@ -0,0 +1,651 @@
+//! Builds a provider from a TOML file laid out like the OpenTelemetry
+//! declarative configuration schema.
+//!
+//! ```toml
+//! file_format = "0.3"
+//!
+//! [resource]
+//! attributes = [{ name = "service.name", value = "checkout" }]
+//!
+//! [meter_provider]
+//! cardinality_limits = { default = 500 }
+//!
+//! [[meter_provider.readers]]
+//! periodic = { interval = 10000, exporter = { console = {} } }
+//!
+//! [[meter_provider.readers]]
+//! periodic = { exporter = { otlp_http = { endpoint = "http://collector:4318/v1/metrics", compression = "gzip" } } }
+//!
+//! [[meter_provider.readers]]
+//! pull = { exporter = { prometheus = { host = "0.0.0.0", port = 9464 } } }
+//!
+//! [[meter_provider.views]]
+//! selector = { instrument_name = "http.*", instrument_type = "counter" }
+//! stream = { aggregation = { drop = {} } }
+//! ```
+//!
+//! Unlike the schema, cardinality limits apply to the whole provider rather
+//! than to each reader. Unknown keys are rejected. Anything the file leaves
+//! out falls back to the environment and then to the SDK defaults, as with
+//! [`MeterProviderBuilder`].
+
+use std::{collections::HashSet, path::Path, time::Duration};
+
+use serde::Deserialize;
+
+use crate::{
+    aggregation::{
+        Aggregation, DEFAULT_HISTOGRAM_BOUNDARIES, EXPONENTIAL_MAX_SCALE, EXPONENTIAL_MAX_SIZE,
+    },
+    common::{Key, KeyValue, Value},
+    error::{MetricsError, MetricsResult},
+    exporter::{Compression, OtlpHttpExporter, StdoutExporter},
+    instrument::InstrumentKind,
+    meter_provider::{MeterProvider, MeterProviderBuilder},
+    metric::Temporality,
+    periodic_reader::PeriodicReader,
+    prometheus_reader::PrometheusReader,
+    resource::Resource,
+    view::View,
+};
+
+/// Reads the file at `path` and builds the provider it describes.
+pub fn load(path: impl AsRef<Path>) -> MetricsResult<MeterProvider> {
+    let path = path.as_ref();
+    let contents = std::fs::read_to_string(path).map_err(|err| {
+        MetricsError::InvalidConfig(format!("cannot read {}: {}", path.display(), err))
+    })?;
+    builder_from_str(&contents)?.build()
+}
+
+/// Parses `contents` into a builder, so that code can add to the
+/// configuration before building.
+pub fn builder_from_str(contents: &str) -> MetricsResult<MeterProviderBuilder> {
+    let config: FileConfig =
+        toml::from_str(contents).map_err(|err| MetricsError::InvalidConfig(err.to_string()))?;
+    config.into_builder()
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileConfig {
+    file_format: String,
+    #[serde(default)]
+    disabled: Option<bool>,
+    #[serde(default)]
+    resource: Option<ResourceConfig>,
+    #[serde(default)]
+    meter_provider: Option<MeterProviderConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ResourceConfig {
+    #[serde(default)]
+    attributes: Vec<AttributeConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AttributeConfig {
+    name: String,
+    value: AttributeValue,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum AttributeValue {
+    Bool(bool),
+    I64(i64),
+    F64(f64),
+    String(String),
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MeterProviderConfig {
+    #[serde(default)]
+    readers: Vec<ReaderConfig>,
+    #[serde(default)]
+    views: Vec<ViewConfig>,
+    #[serde(default)]
+    cardinality_limits: Option<CardinalityLimits>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CardinalityLimits {
+    default: usize,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+enum ReaderConfig {
+    Periodic(PeriodicConfig),
+    Pull(PullConfig),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PeriodicConfig {
+    /// Milliseconds.
+    #[serde(default)]
+    interval: Option<u64>,
+    /// Milliseconds.
+    #[serde(default)]
+    timeout: Option<u64>,
+    exporter: ExporterConfig,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+enum ExporterConfig {
+    Console {
+        #[serde(default)]
+        temporality_preference: Option<TemporalityPreference>,
+    },
+    OtlpHttp(OtlpExporterConfig),
+    /// Needs the `grpc` feature.
+    #[cfg_attr(not(feature = "grpc"), allow(dead_code))]
+    OtlpGrpc(OtlpExporterConfig),
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OtlpExporterConfig {
+    #[serde(default)]
+    endpoint: Option<String>,
+    #[serde(default)]
+    headers: Vec<HeaderConfig>,
+    #[serde(default)]
+    compression: Option<CompressionConfig>,
+    /// Milliseconds.
+    #[serde(default)]
+    timeout: Option<u64>,
+    #[serde(default)]
+    temporality_preference: Option<TemporalityPreference>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HeaderConfig {
+    name: String,
+    value: String,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum CompressionConfig {
+    None,
+    Gzip,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PullConfig {
+    exporter: PullExporterConfig,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+enum PullExporterConfig {
+    Prometheus {
+        #[serde(default = "default_prometheus_host")]
+        host: String,
+        #[serde(default = "default_prometheus_port")]
+        port: u16,
+    },
+}
+
+fn default_prometheus_host() -> String {
+    "localhost".to_string()
+}
+
+fn default_prometheus_port() -> u16 {
+    9464
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum TemporalityPreference {
+    Cumulative,
+    Delta,
+    LowMemory,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ViewConfig {
+    selector: SelectorConfig,
+    #[serde(default)]
+    stream: StreamConfig,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SelectorConfig {
+    #[serde(default)]
+    instrument_name: Option<String>,
+    #[serde(default)]
+    instrument_type: Option<InstrumentType>,
+    #[serde(default)]
+    meter_name: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum InstrumentType {
+    Counter,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StreamConfig {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    aggregation: Option<AggregationConfig>,
+    #[serde(default)]
+    attribute_keys: Option<AttributeKeysConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+enum AggregationConfig {
+    Default {},
+    Drop {},
+    Sum {},
+    LastValue {},
+    ExplicitBucketHistogram {
+        #[serde(default)]
+        boundaries: Option<Vec<f64>>,
+        #[serde(default)]
+        record_min_max: Option<bool>,
+    },
+    Base2ExponentialBucketHistogram {
+        #[serde(default)]
+        max_size: Option<u32>,
+        #[serde(default)]
+        max_scale: Option<i8>,
+        #[serde(default)]
+        record_min_max: Option<bool>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AttributeKeysConfig {
+    #[serde(default)]
+    included: Option<Vec<String>>,
+    #[serde(default)]
+    excluded: Vec<String>,
+}
+
+impl FileConfig {
+    fn into_builder(self) -> MetricsResult<MeterProviderBuilder> {
+        if !self.file_format.starts_with("0.") {
+            return Err(MetricsError::InvalidConfig(format!(
+                "unsupported file_format {:?}",
+                self.file_format
+            )));
+        }
+        let mut builder = MeterProvider::builder();
+        if let Some(disabled) = self.disabled {
+            builder = builder.with_disabled(disabled);
+        }
+        if let Some(resource) = self.resource {
+            builder = builder.with_resource(Resource::new(
+                resource
+                    .attributes
+                    .into_iter()
+                    .map(|attribute| KeyValue::new(Key::from(attribute.name), attribute.value)),
+            ));
+        }
+        let meter_provider = self.meter_provider.unwrap_or_default();
+        if let Some(limits) = meter_provider.cardinality_limits {
+            builder = builder.with_cardinality_limit(limits.default);
+        }
+        for reader in meter_provider.readers {
+            builder = match reader {
+                ReaderConfig::Periodic(periodic) => builder.with_reader(periodic.into_reader()?),
+                ReaderConfig::Pull(PullConfig {
+                    exporter: PullExporterConfig::Prometheus { host, port },
+                }) => builder.with_reader(PrometheusReader::bind((host.as_str(), port))?),
+            };
+        }
+        for view in meter_provider.views {
+            builder = builder.with_view(view.into_view());
+        }
+        Ok(builder)
+    }
+}
+
+impl PeriodicConfig {
+    fn into_reader(self) -> MetricsResult<PeriodicReader> {
+        let mut reader = match self.exporter {
+            ExporterConfig::Console {
+                temporality_preference,
+            } => PeriodicReader::new(
+                temporality_preference
+                    .map(|preference| StdoutExporter::new(preference.into()))
+                    .unwrap_or_default(),
+            ),
+            ExporterConfig::OtlpHttp(config) => {
+                let mut builder = OtlpHttpExporter::builder();
+                if let Some(endpoint) = &config.endpoint {
+                    builder = builder.with_endpoint(endpoint);
+                }
+                for header in &config.headers {
+                    builder = builder.with_header(&header.name, &header.value);
+                }
+                if let Some(compression) = config.compression {
+                    builder = builder.with_compression(compression.into());
+                }
+                if let Some(timeout) = config.timeout {
+                    builder = builder.with_timeout(Duration::from_millis(timeout));
+                }
+                if let Some(preference) = config.temporality_preference {
+                    builder = builder.with_temporality(preference.into());
+                }
+                PeriodicReader::new(builder.build()?)
+            }
+            #[cfg(feature = "grpc")]
+            ExporterConfig::OtlpGrpc(config) => {
+                let mut builder = crate::exporter::OtlpGrpcExporter::builder();
+                if let Some(endpoint) = &config.endpoint {
+                    builder = builder.with_endpoint(endpoint);
+                }
+                for header in &config.headers {
+                    builder = builder.with_metadata(&header.name, &header.value);
+                }
+                if let Some(compression) = config.compression {
+                    builder = builder.with_compression(compression.into());
+                }
+                if let Some(timeout) = config.timeout {
+                    builder = builder.with_timeout(Duration::from_millis(timeout));
+                }
+                if let Some(preference) = config.temporality_preference {
+                    builder = builder.with_temporality(preference.into());
+                }
+                PeriodicReader::new(builder.build()?)
+            }
+            #[cfg(not(feature = "grpc"))]
+            ExporterConfig::OtlpGrpc(_) => {
+                return Err(MetricsError::InvalidConfig(
+                    "the otlp_grpc exporter needs the `grpc` feature".into(),
+                ))
+            }
+        };
+        if let Some(interval) = self.interval {
+            reader = reader.with_interval(Duration::from_millis(interval));
+        }
+        if let Some(timeout) = self.timeout {
+            reader = reader.with_timeout(Duration::from_millis(timeout));
+        }
+        Ok(reader)
+    }
+}
+
+impl From<AttributeValue> for Value {
+    fn from(value: AttributeValue) -> Value {
+        match value {
+            AttributeValue::Bool(value) => value.into(),
+            AttributeValue::I64(value) => value.into(),
+            AttributeValue::F64(value) => value.into(),
+            AttributeValue::String(value) => value.into(),
+        }
+    }
+}
+
+impl From<CompressionConfig> for Compression {
+    fn from(compression: CompressionConfig) -> Compression {
+        match compression {
+            CompressionConfig::None => Compression::None,
+            CompressionConfig::Gzip => Compression::Gzip,
+        }
+    }
+}
+
+impl From<TemporalityPreference> for Temporality {
+    fn from(preference: TemporalityPreference) -> Temporality {
+        match preference {
+            TemporalityPreference::Cumulative => Temporality::Cumulative,
+            // Counters are reported as deltas under the low-memory preference.
+            TemporalityPreference::Delta | TemporalityPreference::LowMemory => Temporality::Delta,
+        }
+    }
+}
+
+impl ViewConfig {
+    fn into_view(self) -> View {
+        let selector = self.selector;
+        let mut view = View::new(selector.instrument_name.as_deref().unwrap_or("*"));
+        if let Some(InstrumentType::Counter) = selector.instrument_type {
+            view = view.with_kind(InstrumentKind::Counter);
+        }
+        if let Some(meter_name) = &selector.meter_name {
+            view = view.with_meter_name(meter_name);
+        }
+
+        let stream = self.stream;
+        if let Some(name) = &stream.name {
+            view = view.with_name(name);
+        }
+        if let Some(description) = &stream.description {
+            view = view.with_description(description);
+        }
+        if let Some(aggregation) = stream.aggregation {
+            view = view.with_aggregation(aggregation.into());
+        }
+        match stream.attribute_keys {
+            Some(AttributeKeysConfig {
+                included: Some(included),
+                excluded,
+            }) => {
+                let excluded: HashSet<String> = excluded.into_iter().collect();
+                view = view.with_allowed_attribute_keys(
+                    included.into_iter().filter(|key| !excluded.contains(key)),
+                );
+            }
+            Some(AttributeKeysConfig {
+                included: None,
+                excluded,
+            }) if !excluded.is_empty() => {
+                view = view.with_denied_attribute_keys(excluded);
+            }
+            _ => {}
+        }
+        view
+    }
+}
+
+impl From<AggregationConfig> for Aggregation {
+    fn from(config: AggregationConfig) -> Aggregation {
+        match config {
+            AggregationConfig::Default {} => Aggregation::Default,
+            AggregationConfig::Drop {} => Aggregation::Drop,
+            AggregationConfig::Sum {} => Aggregation::Sum,
+            AggregationConfig::LastValue {} => Aggregation::LastValue,
+            AggregationConfig::ExplicitBucketHistogram {
+                boundaries,
+                record_min_max,
+            } => Aggregation::ExplicitBucketHistogram {
+                boundaries: boundaries.unwrap_or_else(|| DEFAULT_HISTOGRAM_BOUNDARIES.to_vec()),
+                record_min_max: record_min_max.unwrap_or(true),
+            },
+            AggregationConfig::Base2ExponentialBucketHistogram {
+                max_size,
+                max_scale,
+                record_min_max,
+            } => Aggregation::Base2ExponentialHistogram {
+                max_size: max_size.unwrap_or(EXPONENTIAL_MAX_SIZE),
+                max_scale: max_scale.unwrap_or(EXPONENTIAL_MAX_SCALE),
+                record_min_max: record_min_max.unwrap_or(true),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::{ManualReader, MetricReader};
+
+    fn parse(contents: &str) -> MetricsResult<FileConfig> {
+        toml::from_str(contents).map_err(|err| MetricsError::InvalidConfig(err.to_string()))
+    }
+
+    #[test]
+    fn parses_full_pipeline() {
+        let config = parse(
+            r#"
+            file_format = "0.3"
+
+            [resource]
+            attributes = [
+                { name = "service.name", value = "checkout" },
+                { name = "replica", value = 3 },
+            ]
+
+            [meter_provider]
+            cardinality_limits = { default = 500 }
+
+            [[meter_provider.readers]]
+            periodic = { interval = 5000, timeout = 1000, exporter = { console = { temporality_preference = "cumulative" } } }
+
+            [[meter_provider.readers]]
+            pull = { exporter = { prometheus = { host = "127.0.0.1", port = 0 } } }
+
+            [[meter_provider.views]]
+            selector = { instrument_name = "requests", meter_name = "http" }
+            stream = { name = "http.requests", aggregation = { explicit_bucket_histogram = { boundaries = [1.0, 10.0] } }, attribute_keys = { included = ["route", "host"], excluded = ["host"] } }
+            "#,
+        )
+        .unwrap();
+        let meter_provider = config.meter_provider.as_ref().unwrap();
+        assert_eq!(meter_provider.readers.len(), 2);
+        let view = &meter_provider.views[0];
+        assert_eq!(view.selector.meter_name.as_deref(), Some("http"));
+        assert!(config.into_builder().is_ok());
+    }
+
+    #[test]
+    fn parses_otlp_http_exporter() {
+        let config = parse(
+            r#"
+            file_format = "0.3"
+            [[meter_provider.readers]]
+            periodic = { exporter = { otlp_http = { endpoint = "http://collector:4318/v1/metrics", headers = [{ name = "api-key", value = "secret" }], compression = "gzip", timeout = 2000, temporality_preference = "delta" } } }
+            "#,
+        )
+        .unwrap();
+        let readers = &config.meter_provider.as_ref().unwrap().readers;
+        let ReaderConfig::Periodic(PeriodicConfig {
+            exporter: ExporterConfig::OtlpHttp(otlp),
+            ..
+        }) = &readers[0]
+        else {
+            panic!("expected an otlp_http exporter, got {:?}", readers[0]);
+        };
+        assert_eq!(
+            otlp.endpoint.as_deref(),
+            Some("http://collector:4318/v1/metrics")
+        );
+        assert_eq!(otlp.headers[0].name, "api-key");
+        assert_eq!(otlp.headers[0].value, "secret");
+        assert!(matches!(otlp.compression, Some(CompressionConfig::Gzip)));
+        assert_eq!(otlp.timeout, Some(2000));
+        assert!(matches!(
+            otlp.temporality_preference,
+            Some(TemporalityPreference::Delta)
+        ));
+        assert!(config.into_builder().is_ok());
+    }
+
+    #[test]
+    fn parses_otlp_grpc_exporter() {
+        let config = parse(
+            r#"
+            file_format = "0.3"
+            [[meter_provider.readers]]
+            periodic = { exporter = { otlp_grpc = { endpoint = "http://collector:4317", headers = [{ name = "api-key", value = "secret" }], compression = "none", timeout = 3000, temporality_preference = "cumulative" } } }
+            "#,
+        )
+        .unwrap();
+        let readers = &config.meter_provider.as_ref().unwrap().readers;
+        let ReaderConfig::Periodic(PeriodicConfig {
+            exporter: ExporterConfig::OtlpGrpc(otlp),
+            ..
+        }) = &readers[0]
+        else {
+            panic!("expected an otlp_grpc exporter, got {:?}", readers[0]);
+        };
+        assert_eq!(otlp.endpoint.as_deref(), Some("http://collector:4317"));
+        assert_eq!(otlp.headers.len(), 1);
+        assert!(matches!(otlp.compression, Some(CompressionConfig::None)));
+        assert_eq!(otlp.timeout, Some(3000));
+        assert_eq!(config.into_builder().is_ok(), cfg!(feature = "grpc"));
+    }
+
+    #[test]
+    fn applies_defaults() {
+        let reader = ManualReader::new(Temporality::Cumulative);
+        let provider = builder_from_str(
+            r#"
+            file_format = "0.3"
+            [[meter_provider.views]]
+            selector = { instrument_name = "ignored" }
+            stream = { aggregation = { drop = {} } }
+            "#,
+        )
+        .unwrap()
+        .with_reader(reader.clone())
+        .build()
+        .unwrap();
+        assert_eq!(
+            provider.resource().get("service.name"),
+            Some(&Value::from("unknown_service"))
+        );
+        let meter = provider.get_meter("meter");
+        meter.create_counter("ignored").add(1, &[]);
+        meter.create_counter("kept").add(1, &[]);
+        let collected = reader.collect().unwrap();
+        let names: Vec<_> = collected.metrics().map(|metric| &metric.name).collect();
+        assert_eq!(names, vec!["kept"]);
+
+        let aggregation: Aggregation = AggregationConfig::ExplicitBucketHistogram {
+            boundaries: None,
+            record_min_max: None,
+        }
+        .into();
+        assert_eq!(
+            aggregation,
+            Aggregation::explicit_histogram(DEFAULT_HISTOGRAM_BOUNDARIES.to_vec())
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        for contents in [
+            "file_format = \"0.3\"\nunknown = 1",
+            "file_format = \"0.3\"\n[meter_provider]\nreaderz = []",
+            "file_format = \"0.3\"\n[[meter_provider.readers]]\nperiodic = { exporter = { console = {} }, jitter = 5 }",
+            "file_format = \"0.3\"\n[[meter_provider.readers]]\npull = { exporter = { console = {} } }",
+            "file_format = \"0.3\"\n[[meter_provider.readers]]\nperiodic = { exporter = { otlp = {} } }",
+            "file_format = \"0.3\"\n[[meter_provider.readers]]\nperiodic = { exporter = { otlp_http = { protocol = \"http/protobuf\" } } }",
+            "file_format = \"0.3\"\n[[meter_provider.readers]]\nperiodic = { exporter = { otlp_grpc = { compression = \"zstd\" } } }",
+            "file_format = \"0.3\"\n[[meter_provider.readers]]\npull = { exporter = { prometheus = { path = \"/\" } } }",
+            "file_format = \"0.3\"\n[[meter_provider.views]]\nselector = { instrument_type = \"gauge\" }",
+            "[resource]\nattributes = []",
+        ] {
+            assert!(
+                matches!(builder_from_str(contents), Err(MetricsError::InvalidConfig(_))),
+                "accepted {:?}",
+                contents
+            );
+        }
+        assert!(builder_from_str("file_format = \"1.0\"").is_err());
+    }
+}
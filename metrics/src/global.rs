@@ -0,0 +1,84 @@
+//! Process-wide [`MeterProvider`] for libraries that should not have to take
+//! one as a parameter.
+//!
+//! Until the application calls [`set_meter_provider`], [`meter`] hands out
+//! no-op meters. Instruments keep the provider they were created from, so an
+//! instrument obtained before installation stays a no-op: libraries should
+//! fetch their meters after the application has configured metrics, or fetch
+//! them again when they need the installed provider.
+
+use std::sync::RwLock;
+
+use crate::{
+    meter::Meter, meter_provider::MeterProvider, noop::NoopMeterProvider,
+    scope::InstrumentationScope,
+};
+
+static GLOBAL_METER_PROVIDER: RwLock<Option<MeterProvider>> = RwLock::new(None);
+
+/// Installs `provider` as the global provider, returning the previous one so
+/// the caller can shut it down.
+pub fn set_meter_provider(provider: MeterProvider) -> Option<MeterProvider> {
+    GLOBAL_METER_PROVIDER
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .replace(provider)
+}
+
+/// The installed provider, if any.
+pub fn meter_provider() -> Option<MeterProvider> {
+    GLOBAL_METER_PROVIDER
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+}
+
+/// Returns the meter called `name` from the global provider.
+pub fn meter(name: &str) -> Meter {
+    meter_with_scope(InstrumentationScope::new(name))
+}
+
+/// Returns the meter for `scope` from the global provider, or a no-op meter
+/// when none is installed.
+pub fn meter_with_scope(scope: InstrumentationScope) -> Meter {
+    match meter_provider() {
+        Some(provider) => provider.get_meter_with_scope(scope),
+        None => NoopMeterProvider.get_meter_with_scope(scope),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metric::{MetricData, Temporality};
+    use crate::reader::{ManualReader, MetricReader};
+
+    // The global slot is shared by the whole test binary, so everything that
+    // touches it lives in this one test.
+    #[test]
+    fn noop_until_installed() {
+        assert!(meter_provider().is_none());
+        let early = meter("lib").create_counter("requests");
+        early.add(1, &[]);
+
+        let reader = ManualReader::new(Temporality::Cumulative);
+        let provider = MeterProvider::builder()
+            .with_reader(reader.clone())
+            .build()
+            .unwrap();
+        assert!(set_meter_provider(provider).is_none());
+
+        early.add(1, &[]);
+        meter("lib").create_counter("requests").add(2, &[]);
+        let collected = reader.collect().unwrap();
+        let metrics: Vec<_> = collected.metrics().collect();
+        assert_eq!(metrics.len(), 1);
+        let MetricData::Sum(sum) = &metrics[0].data else {
+            panic!("expected a sum");
+        };
+        assert_eq!(sum.data_points[0].value, 2);
+
+        let previous = set_meter_provider(MeterProvider::new()).unwrap();
+        previous.shutdown().unwrap();
+    }
+}
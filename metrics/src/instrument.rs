@@ -0,0 +1,16 @@
+/// The kind of instrument that produced a measurement.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum InstrumentKind {
+    /// A monotonic counter created with `Meter::create_counter`.
+    Counter,
+}
+
+/// Identifies an instrument when matching views against it.
+#[derive(Clone, Debug)]
+pub struct Instrument {
+    pub name: String,
+    pub description: String,
+    pub unit: String,
+    pub kind: InstrumentKind,
+    pub meter_name: String,
+}
@@ -1,7 +1,24 @@
+pub mod aggregation;
 pub mod attributes;
+pub mod clock;
 pub mod common;
 pub mod counter;
+pub mod env_config;
+pub mod error;
+pub mod exporter;
+pub mod file_config;
+pub mod global;
+pub mod instrument;
 pub mod meter;
 pub mod meter_provider;
 pub mod metric;
 pub mod metricpoint;
+pub mod noop;
+pub mod periodic_reader;
+mod pipeline;
+pub mod prometheus_reader;
+pub mod reader;
+pub mod resource;
+pub mod scope;
+pub mod top;
+pub mod view;
@@ -1,8 +1,13 @@
-use metrics::{common::KeyValue, meter_provider::MeterProvider};
-use std::thread;
+use metrics::{
+    common::KeyValue, exporter::StdoutExporter, meter_provider::MeterProvider,
+    periodic_reader::PeriodicReader,
+};
+use std::{thread, time::Duration};
 
 fn main() {
-    let meter_provider = MeterProvider::new_with_periodic_flush();
+    let reader =
+        PeriodicReader::new(StdoutExporter::default()).with_interval(Duration::from_secs(10));
+    let meter_provider = MeterProvider::new_with_readers(vec![Box::new(reader)], vec![]).unwrap();
     let meter = meter_provider.get_meter("meter");
     let counter = meter.create_counter("counter-name");
     let attributes = [
@@ -3,7 +3,13 @@ use std::{
     sync::{Arc, Mutex},
 };
 
-use crate::{counter::Counter, metric::Metric};
+use crate::{
+    counter::Counter,
+    instrument::{Instrument, InstrumentKind},
+    metric::ScopeMetrics,
+    pipeline::Pipelines,
+    scope::InstrumentationScope,
+};
 
 #[derive(Clone)]
 pub struct Meter {
@@ -11,10 +17,22 @@ pub struct Meter {
 }
 
 impl Meter {
-    pub fn new(name: &str) -> Meter {
+    pub(crate) fn new(scope: InstrumentationScope, pipelines: Arc<Pipelines>) -> Meter {
         Meter {
             inner: Arc::new(MeterInner {
-                name: name.to_string(),
+                scope,
+                pipelines: Some(pipelines),
+                counters: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// A meter whose instruments do nothing.
+    pub(crate) fn noop(scope: InstrumentationScope) -> Meter {
+        Meter {
+            inner: Arc::new(MeterInner {
+                scope,
+                pipelines: None,
                 counters: Mutex::new(HashMap::new()),
             }),
         }
@@ -24,29 +42,51 @@ impl Meter {
         self.inner.create_counter(name)
     }
 
-    pub fn collect(&self) -> Vec<Metric> {
+    pub fn scope(&self) -> &InstrumentationScope {
+        &self.inner.scope
+    }
+
+    pub(crate) fn collect(&self, pipeline: usize) -> ScopeMetrics {
         let mut metrics = vec![];
         let counters = self.inner.counters.lock().unwrap();
         for counter in counters.values() {
-            metrics.push(counter.collect());
+            metrics.append(&mut counter.collect(pipeline));
         }
 
-        metrics
+        ScopeMetrics {
+            scope: self.inner.scope.clone(),
+            metrics,
+        }
     }
 }
 
 pub struct MeterInner {
-    name: String,
+    scope: InstrumentationScope,
+    /// `None` for a no-op meter.
+    pipelines: Option<Arc<Pipelines>>,
     counters: Mutex<HashMap<String, Counter>>,
 }
 
 impl MeterInner {
     pub fn create_counter(&self, name: &str) -> Counter {
+        let Some(pipelines) = &self.pipelines else {
+            return Counter::noop();
+        };
         let mut counters = self.counters.lock().unwrap();
         if let Some(counter) = counters.get(name) {
             counter.clone()
         } else {
-            let counter = Counter::new(name.to_string());
+            let instrument = Instrument {
+                name: name.to_string(),
+                description: String::new(),
+                unit: String::new(),
+                kind: InstrumentKind::Counter,
+                meter_name: self.scope.name().to_string(),
+            };
+            let counter = Counter::new(
+                pipelines.counter_streams(&instrument),
+                pipelines.is_shutdown.clone(),
+            );
             counters.insert(name.to_string(), counter.clone());
             counter
         }
@@ -1,78 +1,670 @@
 use std::{
     collections::HashMap,
-    sync::{Arc, Mutex},
+    sync::{atomic::Ordering, Arc, Mutex},
+    time::{Duration, Instant},
     vec,
 };
 
-use crate::{meter::Meter, metric::Metric};
+use crate::{
+    clock::{Clock, SystemClock},
+    env_config::EnvConfig,
+    error::{default_error_handler, ErrorHandler, MetricsError, MetricsResult},
+    meter::Meter,
+    metric::{Metric, ResourceMetrics, Temporality},
+    pipeline::{Pipelines, StreamContext},
+    reader::{ManualReader, MetricProducer, MetricReader},
+    resource::Resource,
+    scope::InstrumentationScope,
+    view::View,
+};
+
+const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_CARDINALITY_LIMIT: usize = 2000;
 
+/// Creates meters and owns the readers that collect from them.
+///
+/// Dropping the last clone of a provider shuts it down, which gives readers
+/// the chance to export their final collection.
 #[derive(Clone)]
 pub struct MeterProvider {
     inner: Arc<MeterProviderInner>,
+    default_reader: Option<ManualReader>,
+    _shutdown_on_drop: Arc<ShutdownOnDrop>,
+}
+
+struct ShutdownOnDrop(Arc<MeterProviderInner>);
+
+impl Drop for ShutdownOnDrop {
+    fn drop(&mut self) {
+        if let Err(err) = self.0.shutdown(DEFAULT_SHUTDOWN_TIMEOUT) {
+            (self.0.pipelines.context.error_handler)(err);
+        }
+    }
+}
+
+impl Default for MeterProvider {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MeterProvider {
+    /// Creates a provider with a single delta reader, collected through
+    /// [`MeterProvider::collect`].
     pub fn new() -> MeterProvider {
-        MeterProvider {
-            inner: Arc::new(MeterProviderInner::new()),
-        }
+        Self::new_with_default_reader(vec![]).expect("default configuration is valid")
     }
 
-    pub fn new_with_periodic_flush() -> MeterProvider {
-        let mp = MeterProvider {
-            inner: Arc::new(MeterProviderInner::new()),
-        };
+    /// Starts configuring a provider.
+    ///
+    /// ```
+    /// use metrics::common::KeyValue;
+    /// use metrics::meter_provider::MeterProvider;
+    /// use metrics::metric::Temporality;
+    /// use metrics::reader::ManualReader;
+    /// use metrics::resource::Resource;
+    ///
+    /// let reader = ManualReader::new(Temporality::Cumulative);
+    /// let provider = MeterProvider::builder()
+    ///     .with_resource(Resource::new([KeyValue::new("service.name", "checkout")]))
+    ///     .with_reader(reader.clone())
+    ///     .with_cardinality_limit(500)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn builder() -> MeterProviderBuilder {
+        MeterProviderBuilder::default()
+    }
 
-        let mp_clone = mp.clone();
-        std::thread::spawn(move || loop {
-            std::thread::sleep(std::time::Duration::from_secs(10));
-            let metrics = mp_clone.collect();
-            for metric in metrics {
-                println!("{:?}", metric);
-            }
-        });
+    /// Creates a provider whose instruments are shaped by `views`.
+    ///
+    /// Instruments matched by no view keep their default stream; each
+    /// matching view adds one stream.
+    pub fn new_with_views(views: Vec<View>) -> MetricsResult<MeterProvider> {
+        Self::new_with_default_reader(views)
+    }
 
-        mp
+    /// Creates a provider feeding every reader in `readers`.
+    ///
+    /// Each reader gets independent streams with its own temporality, so
+    /// collecting through one never affects what the others see. Such a
+    /// provider has no default reader: collect through the readers instead of
+    /// [`MeterProvider::collect`].
+    pub fn new_with_readers(
+        readers: Vec<Box<dyn MetricReader>>,
+        views: Vec<View>,
+    ) -> MetricsResult<MeterProvider> {
+        MeterProviderBuilder {
+            readers,
+            views,
+            ..Default::default()
+        }
+        .build()
+    }
+
+    fn new_with_default_reader(views: Vec<View>) -> MetricsResult<MeterProvider> {
+        let reader = ManualReader::new(Temporality::Delta);
+        let mut provider = MeterProvider::builder()
+            .with_reader(reader.clone())
+            .with_views(views)
+            .build()?;
+        provider.default_reader = Some(reader);
+        Ok(provider)
     }
 
     pub fn get_meter(&self, name: &str) -> Meter {
-        self.inner.get_meter(name)
+        self.inner.get_meter(InstrumentationScope::new(name))
+    }
+
+    /// Returns the meter for `scope`, creating it on first use.
+    pub fn get_meter_with_scope(&self, scope: InstrumentationScope) -> Meter {
+        self.inner.get_meter(scope)
     }
 
-    pub fn collect(&self) -> Vec<Metric> {
-        self.inner.collect()
+    /// The resource describing the entity this provider reports for.
+    pub fn resource(&self) -> &Resource {
+        &self.inner.resource
+    }
+    /// Collects the deltas since the previous call from the default reader.
+    ///
+    /// Providers created with explicit readers have no default reader and
+    /// return [`MetricsError::ReaderNotRegistered`].
+    pub fn collect(&self) -> MetricsResult<Vec<Metric>> {
+        let reader = self
+            .default_reader
+            .as_ref()
+            .ok_or(MetricsError::ReaderNotRegistered)?;
+        Ok(reader.collect()?.metrics().cloned().collect())
+    }
+
+    /// Makes every reader export what it has collected so far, waiting up to
+    /// 30 seconds in total.
+    pub fn force_flush(&self) -> MetricsResult<()> {
+        self.force_flush_with_timeout(DEFAULT_SHUTDOWN_TIMEOUT)
+    }
+
+    pub fn force_flush_with_timeout(&self, timeout: Duration) -> MetricsResult<()> {
+        self.inner.force_flush(timeout)
+    }
+
+    /// Flushes and shuts down every reader, waiting up to 30 seconds in total.
+    ///
+    /// Instruments become no-ops from this point on. Calling it again has no
+    /// effect.
+    pub fn shutdown(&self) -> MetricsResult<()> {
+        self.shutdown_with_timeout(DEFAULT_SHUTDOWN_TIMEOUT)
+    }
+
+    pub fn shutdown_with_timeout(&self, timeout: Duration) -> MetricsResult<()> {
+        self.inner.shutdown(timeout)
     }
 }
 
-struct MeterProviderInner {
-    meters: Mutex<HashMap<String, Meter>>,
+/// Configures a [`MeterProvider`]. Settings are checked by
+/// [`MeterProviderBuilder::build`].
+///
+/// Settings left unset are taken from the environment, see
+/// [`env_config`](crate::env_config).
+pub struct MeterProviderBuilder {
+    resource: Resource,
+    readers: Vec<Box<dyn MetricReader>>,
+    views: Vec<View>,
+    cardinality_limit: Option<usize>,
+    disabled: Option<bool>,
+    error_handler: ErrorHandler,
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for MeterProviderBuilder {
+    fn default() -> Self {
+        MeterProviderBuilder {
+            resource: Resource::default(),
+            readers: vec![],
+            views: vec![],
+            cardinality_limit: None,
+            disabled: None,
+            error_handler: default_error_handler(),
+            clock: Arc::new(SystemClock),
+        }
+    }
+}
+
+impl MeterProviderBuilder {
+    /// Describes the entity the provider reports for.
+    ///
+    /// `resource` is merged over `Resource::default()`, so explicit attributes
+    /// win over those detected from the environment, which in turn win over
+    /// the SDK defaults.
+    pub fn with_resource(mut self, resource: Resource) -> Self {
+        self.resource = self.resource.merge(&resource);
+        self
+    }
+
+    /// Adds a reader; each reader collects independently.
+    pub fn with_reader(mut self, reader: impl MetricReader + 'static) -> Self {
+        self.readers.push(Box::new(reader));
+        self
+    }
+
+    pub fn with_view(mut self, view: View) -> Self {
+        self.views.push(view);
+        self
+    }
+
+    pub fn with_views(mut self, views: impl IntoIterator<Item = View>) -> Self {
+        self.views.extend(views);
+        self
+    }
+
+    /// Maximum number of attribute sets per metric stream, including the
+    /// overflow set. Defaults to `OTEL_METRICS_CARDINALITY_LIMIT`, or 2000.
+    pub fn with_cardinality_limit(mut self, limit: usize) -> Self {
+        self.cardinality_limit = Some(limit);
+        self
+    }
+
+    /// Builds a provider without readers, whose instruments are no-ops.
+    /// Defaults to `OTEL_SDK_DISABLED`.
+    pub fn with_disabled(mut self, disabled: bool) -> Self {
+        self.disabled = Some(disabled);
+        self
+    }
+
+    /// Receives errors that cannot be returned to a caller, such as failed
+    /// periodic exports. Defaults to printing them to stderr.
+    pub fn with_error_handler(
+        mut self,
+        handler: impl Fn(MetricsError) + Send + Sync + 'static,
+    ) -> Self {
+        self.error_handler = Arc::new(handler);
+        self
+    }
+
+    /// Source of data point timestamps. Defaults to the system clock.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Builds the provider.
+    ///
+    /// A disabled provider gets no readers and all of its instruments are
+    /// no-ops. Without explicit readers, readers for the
+    /// exporters in `OTEL_METRICS_EXPORTER` are added.
+    pub fn build(self) -> MetricsResult<MeterProvider> {
+        let (env, errors) = EnvConfig::from_env();
+        for err in errors {
+            (self.error_handler)(err);
+        }
+        self.build_with_env(env)
+    }
+
+    fn build_with_env(self, env: EnvConfig) -> MetricsResult<MeterProvider> {
+        for view in &self.views {
+            view.validate()?;
+        }
+        let cardinality_limit = self
+            .cardinality_limit
+            .or(env.cardinality_limit)
+            .unwrap_or(DEFAULT_CARDINALITY_LIMIT);
+        if cardinality_limit < 2 {
+            return Err(MetricsError::InvalidConfig(
+                "cardinality limit must leave room for the overflow point".into(),
+            ));
+        }
+        let readers = if self.disabled.unwrap_or(env.disabled) {
+            vec![]
+        } else if self.readers.is_empty() {
+            let mut errors = vec![];
+            let readers = env.readers(&mut errors);
+            for err in errors {
+                (self.error_handler)(err);
+            }
+            readers
+        } else {
+            self.readers
+        };
+
+        let context = StreamContext {
+            clock: self.clock,
+            error_handler: self.error_handler,
+            cardinality_limit,
+        };
+        let inner =
+            MeterProviderInner::new(self.resource, Pipelines::new(self.views, readers, context));
+        Ok(MeterProvider {
+            _shutdown_on_drop: Arc::new(ShutdownOnDrop(inner.clone())),
+            inner,
+            default_reader: None,
+        })
+    }
+}
+
+pub(crate) struct MeterProviderInner {
+    resource: Resource,
+    pipelines: Arc<Pipelines>,
+    meters: Mutex<HashMap<InstrumentationScope, Meter>>,
 }
 
 impl MeterProviderInner {
-    fn new() -> MeterProviderInner {
-        MeterProviderInner {
-            meters: Mutex::new(HashMap::new()),
+    fn new(resource: Resource, pipelines: Pipelines) -> Arc<MeterProviderInner> {
+        Arc::new_cyclic(|provider| {
+            for (pipeline, reader) in pipelines.readers.iter().enumerate() {
+                reader.register_producer(MetricProducer::new(
+                    provider.clone(),
+                    pipeline,
+                    pipelines.context.error_handler.clone(),
+                ));
+            }
+            MeterProviderInner {
+                resource,
+                pipelines: Arc::new(pipelines),
+                meters: Mutex::new(HashMap::new()),
+            }
+        })
+    }
+
+    fn force_flush(&self, timeout: Duration) -> MetricsResult<()> {
+        if self.pipelines.is_shutdown.load(Ordering::Acquire) {
+            return Err(MetricsError::AlreadyShutdown);
         }
+        self.for_each_reader(timeout, |reader, remaining| reader.force_flush(remaining))
     }
 
-    fn get_meter(&self, name: &str) -> Meter {
+    fn shutdown(&self, timeout: Duration) -> MetricsResult<()> {
+        if self.pipelines.is_shutdown.swap(true, Ordering::AcqRel) {
+            return Ok(());
+        }
+        self.for_each_reader(timeout, |reader, remaining| reader.shutdown(remaining))
+    }
+
+    /// Runs `action` on every reader, sharing `timeout` between them. Returns
+    /// the first error, after every reader has been visited.
+    fn for_each_reader(
+        &self,
+        timeout: Duration,
+        action: impl Fn(&dyn MetricReader, Duration) -> MetricsResult<()>,
+    ) -> MetricsResult<()> {
+        let deadline = Instant::now() + timeout;
+        let mut result = Ok(());
+        for reader in &self.pipelines.readers {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let outcome = action(reader.as_ref(), remaining);
+            if result.is_ok() {
+                result = outcome;
+            }
+        }
+        result
+    }
+
+    fn get_meter(&self, scope: InstrumentationScope) -> Meter {
         let mut meters = self.meters.lock().unwrap();
-        if let Some(meter) = meters.get(name) {
+        if let Some(meter) = meters.get(&scope) {
             meter.clone()
         } else {
-            let meter = Meter::new(name);
-            meters.insert(name.to_string(), meter.clone());
+            let meter = Meter::new(scope.clone(), self.pipelines.clone());
+            meters.insert(scope, meter.clone());
             meter
         }
     }
 
-    pub fn collect(&self) -> Vec<Metric> {
-        let mut metrics: Vec<Metric> = vec![];
+    pub(crate) fn collect(&self, pipeline: usize) -> ResourceMetrics {
+        let mut scope_metrics = vec![];
         let meters = self.meters.lock().unwrap();
         for meter in meters.values() {
-            metrics.append(meter.collect().as_mut());
+            let collected = meter.collect(pipeline);
+            if !collected.metrics.is_empty() {
+                scope_metrics.push(collected);
+            }
+        }
+
+        ResourceMetrics {
+            resource: self.resource.clone(),
+            scope_metrics,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aggregation::Aggregation;
+    use crate::common::KeyValue;
+    use crate::instrument::InstrumentKind;
+    use crate::metric::MetricData;
+
+    fn sum_value(metrics: &[Metric]) -> u64 {
+        match &metrics[0].data {
+            MetricData::Sum(sum) => sum.data_points[0].value,
+            _ => panic!("expected a sum"),
+        }
+    }
+
+    #[test]
+    fn views_rename_and_reaggregate() {
+        let provider = MeterProvider::new_with_views(vec![
+            View::new("requests")
+                .with_name("http.requests")
+                .with_description("Incoming requests"),
+            View::new("latency")
+                .with_meter_name("server")
+                .with_aggregation(Aggregation::explicit_histogram(vec![10.0])),
+            View::new("noisy.*").with_aggregation(Aggregation::Drop),
+        ])
+        .unwrap();
+        let meter = provider.get_meter("server");
+        meter.create_counter("requests").add(1, &[]);
+        meter.create_counter("latency").add(20, &[]);
+        meter.create_counter("noisy.debug").add(1, &[]);
+
+        let mut metrics = provider.collect().unwrap();
+        metrics.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(metrics.len(), 2);
+        assert_eq!(metrics[0].name, "http.requests");
+        assert_eq!(metrics[0].description, "Incoming requests");
+        assert!(matches!(metrics[0].data, MetricData::Sum(_)));
+        assert_eq!(metrics[1].name, "latency");
+        let MetricData::Histogram(histogram) = &metrics[1].data else {
+            panic!("expected a histogram");
+        };
+        assert_eq!(histogram.data_points[0].bucket_counts, vec![0, 1]);
+    }
+
+    #[test]
+    fn invalid_views_are_rejected() {
+        let result = MeterProvider::new_with_views(vec![View::new("*").with_name("all")]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn readers_collect_independently() {
+        let delta = ManualReader::new(Temporality::Delta);
+        let cumulative = ManualReader::new(Temporality::Cumulative)
+            .with_aggregation(InstrumentKind::Counter, Aggregation::LastValue);
+        let provider = MeterProvider::new_with_readers(
+            vec![Box::new(delta.clone()), Box::new(cumulative.clone())],
+            vec![],
+        )
+        .unwrap();
+        let counter = provider.get_meter("meter").create_counter("requests");
+
+        counter.add(2, &[]);
+        assert_eq!(
+            sum_value(&delta.collect().unwrap().scope_metrics[0].metrics),
+            2
+        );
+        counter.add(3, &[]);
+        assert_eq!(
+            sum_value(&delta.collect().unwrap().scope_metrics[0].metrics),
+            3
+        );
+
+        let metrics = cumulative.collect().unwrap().scope_metrics[0]
+            .metrics
+            .clone();
+        let MetricData::Gauge(gauge) = &metrics[0].data else {
+            panic!("expected a gauge");
+        };
+        assert_eq!(gauge.data_points[0].value, 3);
+        assert!(matches!(
+            provider.collect(),
+            Err(MetricsError::ReaderNotRegistered)
+        ));
+    }
+
+    #[test]
+    fn reader_outliving_provider_reports_error() {
+        let reader = ManualReader::new(Temporality::Delta);
+        assert!(reader.collect().is_err());
+        let provider =
+            MeterProvider::new_with_readers(vec![Box::new(reader.clone())], vec![]).unwrap();
+        assert!(reader.collect().is_ok());
+        drop(provider);
+        assert!(reader.collect().is_err());
+    }
+
+    #[test]
+    fn shutdown_stops_recording_and_is_idempotent() {
+        let reader = ManualReader::new(Temporality::Cumulative);
+        let provider =
+            MeterProvider::new_with_readers(vec![Box::new(reader.clone())], vec![]).unwrap();
+        let counter = provider.get_meter("meter").create_counter("requests");
+        counter.add(1, &[]);
+        provider.force_flush().unwrap();
+
+        provider.shutdown().unwrap();
+        provider.shutdown().unwrap();
+        counter.add(1, &[]);
+        assert_eq!(reader.collect().unwrap_err(), MetricsError::AlreadyShutdown);
+        assert_eq!(provider.force_flush(), Err(MetricsError::AlreadyShutdown));
+    }
+
+    #[test]
+    fn dropping_last_handle_shuts_down() {
+        let reader = ManualReader::new(Temporality::Delta);
+        let provider =
+            MeterProvider::new_with_readers(vec![Box::new(reader.clone())], vec![]).unwrap();
+        let clone = provider.clone();
+        drop(provider);
+        assert!(reader.collect().is_ok());
+        drop(clone);
+        assert_eq!(reader.collect().unwrap_err(), MetricsError::AlreadyShutdown);
+    }
+
+    #[test]
+    fn builder_applies_settings() {
+        use std::time::{SystemTime, UNIX_EPOCH};
+
+        struct FixedClock;
+        impl Clock for FixedClock {
+            fn now(&self) -> SystemTime {
+                UNIX_EPOCH + Duration::from_secs(42)
+            }
         }
 
-        metrics
+        let errors = Arc::new(Mutex::new(vec![]));
+        let errors_clone = errors.clone();
+        let reader = ManualReader::new(Temporality::Delta);
+        let provider = MeterProvider::builder()
+            .with_resource(Resource::new([KeyValue::new("service.name", "checkout")]))
+            .with_reader(reader.clone())
+            .with_view(View::new("requests").with_name("http.requests"))
+            .with_cardinality_limit(2)
+            .with_error_handler(move |err| errors_clone.lock().unwrap().push(err))
+            .with_clock(FixedClock)
+            .build()
+            .unwrap();
+        assert_eq!(
+            provider.resource().get("service.name"),
+            Some(&"checkout".into())
+        );
+
+        let counter = provider.get_meter("meter").create_counter("requests");
+        counter.add(1, &[KeyValue::new("user", "a")]);
+        counter.add(1, &[KeyValue::new("user", "b")]);
+
+        let collected = reader.collect().unwrap();
+        assert_eq!(
+            collected.resource.get("service.name"),
+            Some(&"checkout".into())
+        );
+        let metrics: Vec<Metric> = collected.metrics().cloned().collect();
+        assert_eq!(metrics[0].name, "http.requests");
+        let MetricData::Sum(sum) = &metrics[0].data else {
+            panic!("expected a sum");
+        };
+        assert_eq!(sum.data_points.len(), 2);
+        assert_eq!(
+            sum.data_points[0].time,
+            UNIX_EPOCH + Duration::from_secs(42)
+        );
+        assert_eq!(
+            *errors.lock().unwrap(),
+            vec![MetricsError::CardinalityLimitReached(
+                "http.requests".into()
+            )]
+        );
+    }
+
+    #[test]
+    fn builder_rejects_invalid_settings() {
+        assert!(MeterProvider::builder()
+            .with_cardinality_limit(1)
+            .build()
+            .is_err());
+        assert!(MeterProvider::builder()
+            .with_view(View::new("*").with_name("all"))
+            .build()
+            .is_err());
+        let provider = MeterProvider::builder().build().unwrap();
+        assert_eq!(
+            provider.resource().get("telemetry.sdk.language"),
+            Some(&"rust".into())
+        );
+    }
+
+    #[test]
+    fn environment_fills_unset_settings() {
+        let env = || EnvConfig {
+            cardinality_limit: Some(2),
+            ..Default::default()
+        };
+        let reader = ManualReader::new(Temporality::Cumulative);
+        let provider = MeterProvider::builder()
+            .with_reader(reader.clone())
+            .build_with_env(env())
+            .unwrap();
+        let counter = provider.get_meter("meter").create_counter("requests");
+        counter.add(1, &[KeyValue::new("a", 1)]);
+        counter.add(1, &[KeyValue::new("a", 2)]);
+        let collected = reader.collect().unwrap();
+        let MetricData::Sum(sum) = &collected.metrics().next().unwrap().data else {
+            panic!("expected a sum");
+        };
+        assert_eq!(sum.data_points.len(), 2);
+
+        // explicit settings win
+        let provider = MeterProvider::builder()
+            .with_cardinality_limit(10)
+            .build_with_env(env())
+            .unwrap();
+        assert_eq!(provider.inner.pipelines.context.cardinality_limit, 10);
+
+        let reader = ManualReader::new(Temporality::Cumulative);
+        let provider = MeterProvider::builder()
+            .with_reader(reader.clone())
+            .build_with_env(EnvConfig {
+                disabled: true,
+                ..Default::default()
+            })
+            .unwrap();
+        provider
+            .get_meter("meter")
+            .create_counter("requests")
+            .add(1, &[]);
+        assert_eq!(
+            reader.collect().err(),
+            Some(MetricsError::ReaderNotRegistered)
+        );
+    }
+
+    #[test]
+    fn meters_are_keyed_and_grouped_by_scope() {
+        let reader = ManualReader::new(Temporality::Delta);
+        let provider = MeterProvider::builder()
+            .with_reader(reader.clone())
+            .build()
+            .unwrap();
+        let v1 = InstrumentationScope::new("lib")
+            .with_version("1.0")
+            .with_schema_url("https://opentelemetry.io/schemas/1.21.0")
+            .with_attributes([KeyValue::new("b", 1), KeyValue::new("a", 1)]);
+        let v1_reordered = InstrumentationScope::new("lib")
+            .with_version("1.0")
+            .with_schema_url("https://opentelemetry.io/schemas/1.21.0")
+            .with_attributes([KeyValue::new("a", 1), KeyValue::new("b", 1)]);
+        let v2 = InstrumentationScope::new("lib").with_version("2.0");
+
+        provider
+            .get_meter_with_scope(v1.clone())
+            .create_counter("requests")
+            .add(1, &[]);
+        provider
+            .get_meter_with_scope(v1_reordered)
+            .create_counter("requests")
+            .add(1, &[]);
+        provider
+            .get_meter_with_scope(v2.clone())
+            .create_counter("requests")
+            .add(5, &[]);
+        provider.get_meter("idle");
+
+        let mut scope_metrics = reader.collect().unwrap().scope_metrics;
+        scope_metrics.sort_by(|a, b| a.scope.version().cmp(&b.scope.version()));
+        assert_eq!(scope_metrics.len(), 2);
+        assert_eq!(scope_metrics[0].scope, v1);
+        assert_eq!(sum_value(&scope_metrics[0].metrics), 2);
+        assert_eq!(scope_metrics[1].scope, v2);
+        assert_eq!(sum_value(&scope_metrics[1].metrics), 5);
     }
 }
@@ -1,15 +1,127 @@
+use std::time::SystemTime;
+
 use crate::common::KeyValue;
+use crate::resource::Resource;
+use crate::scope::InstrumentationScope;
+
+/// Everything a reader collected in one go, with the resource it describes.
+#[derive(Debug, Clone)]
+pub struct ResourceMetrics {
+    pub resource: Resource,
+    pub scope_metrics: Vec<ScopeMetrics>,
+}
+
+impl ResourceMetrics {
+    /// All metrics, regardless of scope.
+    pub fn metrics(&self) -> impl Iterator<Item = &Metric> {
+        self.scope_metrics
+            .iter()
+            .flat_map(|scope| scope.metrics.iter())
+    }
+}
+
+/// The metrics produced by one meter.
+#[derive(Debug, Clone)]
+pub struct ScopeMetrics {
+    pub scope: InstrumentationScope,
+    pub metrics: Vec<Metric>,
+}
 
-#[derive(Debug)]
+/// One metric stream as produced by a collection.
+#[derive(Debug, Clone)]
 pub struct Metric {
     pub name: String,
-    pub metric_points: Vec<(Vec<KeyValue>, u32)>,
+    pub description: String,
+    pub unit: String,
+    pub data: MetricData,
 }
+
 impl Metric {
-    pub(crate) fn new(name: String, points: Vec<(Vec<KeyValue>, u32)>) -> Self {
+    pub(crate) fn new(name: String, description: String, unit: String, data: MetricData) -> Self {
         Self {
-            name: name,
-            metric_points: points,
+            name,
+            description,
+            unit,
+            data,
         }
     }
 }
+
+/// Whether reported values cover the whole lifetime of a stream or only the
+/// interval since the previous collection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Temporality {
+    Cumulative,
+    Delta,
+}
+
+/// The aggregated data points of a metric stream.
+#[derive(Debug, Clone)]
+pub enum MetricData {
+    Sum(Sum),
+    Gauge(Gauge),
+    Histogram(Histogram),
+    ExponentialHistogram(ExponentialHistogram),
+}
+
+#[derive(Debug, Clone)]
+pub struct Sum {
+    pub data_points: Vec<DataPoint>,
+    pub temporality: Temporality,
+    pub is_monotonic: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Gauge {
+    pub data_points: Vec<DataPoint>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DataPoint {
+    pub attributes: Vec<KeyValue>,
+    pub start_time: SystemTime,
+    pub time: SystemTime,
+    pub value: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    pub data_points: Vec<HistogramDataPoint>,
+    pub temporality: Temporality,
+}
+
+#[derive(Debug, Clone)]
+pub struct HistogramDataPoint {
+    pub attributes: Vec<KeyValue>,
+    pub start_time: SystemTime,
+    pub time: SystemTime,
+    pub count: u64,
+    pub sum: u64,
+    pub min: Option<u64>,
+    pub max: Option<u64>,
+    /// Upper inclusive bounds; there is one more bucket than bounds.
+    pub bounds: Vec<f64>,
+    pub bucket_counts: Vec<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExponentialHistogram {
+    pub data_points: Vec<ExponentialHistogramDataPoint>,
+    pub temporality: Temporality,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExponentialHistogramDataPoint {
+    pub attributes: Vec<KeyValue>,
+    pub start_time: SystemTime,
+    pub time: SystemTime,
+    pub count: u64,
+    pub sum: u64,
+    pub min: Option<u64>,
+    pub max: Option<u64>,
+    pub scale: i8,
+    pub zero_count: u64,
+    /// Bucket `offset + i` counts values in `(base^(offset+i), base^(offset+i+1)]`.
+    pub positive_offset: i32,
+    pub positive_bucket_counts: Vec<u64>,
+}
@@ -1,4 +1,9 @@
-use std::sync::{atomic::AtomicU64, Arc};
+use std::sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    Arc, Mutex,
+};
+
+use crate::aggregation::{Aggregation, ExponentialBuckets, HistogramBuckets};
 
 #[derive(Clone)]
 pub struct MetricPoint {
@@ -6,9 +11,9 @@ pub struct MetricPoint {
 }
 
 impl MetricPoint {
-    pub fn new() -> MetricPoint {
+    pub(crate) fn new(aggregation: &Aggregation) -> MetricPoint {
         MetricPoint {
-            inner: Arc::new(MetricPointInner::new()),
+            inner: Arc::new(MetricPointInner::new(aggregation)),
         }
     }
 
@@ -16,36 +21,125 @@ impl MetricPoint {
         self.inner.add(value);
     }
 
-    pub fn get_sum(&self) -> u32 {
-        self.inner.get_sum()
+    /// Returns the aggregated value, or `None` if nothing was recorded since
+    /// the last reset.
+    pub(crate) fn collect(&self, reset: bool) -> Option<PointValue> {
+        self.inner.collect(reset)
     }
+}
 
-    pub fn reset(&self) {
-        self.inner.reset();
-    }
+/// Snapshot of a single point's aggregated value.
+pub(crate) enum PointValue {
+    Sum(u64),
+    LastValue(u64),
+    Histogram(HistogramBuckets),
+    ExponentialHistogram(ExponentialBuckets),
 }
 
 pub struct MetricPointInner {
-    sum: AtomicU64,
+    updated: AtomicBool,
+    aggregator: Aggregator,
+}
+
+enum Aggregator {
+    Sum(AtomicU64),
+    LastValue(AtomicU64),
+    Histogram {
+        boundaries: Vec<f64>,
+        buckets: Mutex<HistogramBuckets>,
+    },
+    ExponentialHistogram {
+        max_size: u32,
+        max_scale: i8,
+        buckets: Mutex<ExponentialBuckets>,
+    },
 }
 
 impl MetricPointInner {
-    fn new() -> MetricPointInner {
+    fn new(aggregation: &Aggregation) -> MetricPointInner {
+        let aggregator = match aggregation {
+            Aggregation::LastValue => Aggregator::LastValue(AtomicU64::new(0)),
+            Aggregation::ExplicitBucketHistogram { boundaries, .. } => Aggregator::Histogram {
+                boundaries: boundaries.clone(),
+                buckets: Mutex::new(HistogramBuckets::new(boundaries.len() + 1)),
+            },
+            Aggregation::Base2ExponentialHistogram {
+                max_size,
+                max_scale,
+                ..
+            } => Aggregator::ExponentialHistogram {
+                max_size: *max_size,
+                max_scale: *max_scale,
+                buckets: Mutex::new(ExponentialBuckets::new(*max_size, *max_scale)),
+            },
+            _ => Aggregator::Sum(AtomicU64::new(0)),
+        };
         MetricPointInner {
-            sum: AtomicU64::new(1),
+            updated: AtomicBool::new(false),
+            aggregator,
         }
     }
 
-    fn get_sum(&self) -> u32 {
-        self.sum.load(std::sync::atomic::Ordering::Relaxed) as u32
-    }
-
     fn add(&self, value: u32) {
-        self.sum
-            .fetch_add(value as u64, std::sync::atomic::Ordering::Relaxed);
+        match &self.aggregator {
+            Aggregator::Sum(sum) => {
+                sum.fetch_add(value as u64, Ordering::Relaxed);
+            }
+            Aggregator::LastValue(last) => last.store(value as u64, Ordering::Relaxed),
+            Aggregator::Histogram {
+                boundaries,
+                buckets,
+            } => buckets.lock().unwrap().record(boundaries, value as u64),
+            Aggregator::ExponentialHistogram { buckets, .. } => {
+                buckets.lock().unwrap().record(value as u64)
+            }
+        }
+        // Avoid dirtying the cache line when the flag is already set.
+        if !self.updated.load(Ordering::Relaxed) {
+            self.updated.store(true, Ordering::Release);
+        }
     }
 
-    pub fn reset(&self) {
-        self.sum.store(0, std::sync::atomic::Ordering::Relaxed);
+    fn collect(&self, reset: bool) -> Option<PointValue> {
+        let updated = if reset {
+            self.updated.swap(false, Ordering::AcqRel)
+        } else {
+            self.updated.load(Ordering::Acquire)
+        };
+        if !updated {
+            return None;
+        }
+
+        let value = match &self.aggregator {
+            Aggregator::Sum(sum) if reset => PointValue::Sum(sum.swap(0, Ordering::Relaxed)),
+            Aggregator::Sum(sum) => PointValue::Sum(sum.load(Ordering::Relaxed)),
+            Aggregator::LastValue(last) => PointValue::LastValue(last.load(Ordering::Relaxed)),
+            Aggregator::Histogram {
+                boundaries,
+                buckets,
+            } => {
+                let mut buckets = buckets.lock().unwrap();
+                if reset {
+                    let fresh = HistogramBuckets::new(boundaries.len() + 1);
+                    PointValue::Histogram(std::mem::replace(&mut *buckets, fresh))
+                } else {
+                    PointValue::Histogram(buckets.clone())
+                }
+            }
+            Aggregator::ExponentialHistogram {
+                max_size,
+                max_scale,
+                buckets,
+            } => {
+                let mut buckets = buckets.lock().unwrap();
+                if reset {
+                    let fresh = ExponentialBuckets::new(*max_size, *max_scale);
+                    PointValue::ExponentialHistogram(std::mem::replace(&mut *buckets, fresh))
+                } else {
+                    PointValue::ExponentialHistogram(buckets.clone())
+                }
+            }
+        };
+        Some(value)
     }
 }
@@ -0,0 +1,40 @@
+//! A provider whose meters and instruments do nothing.
+//!
+//! Libraries can record unconditionally against a [`NoopMeterProvider`]
+//! meter; recording on its instruments costs a single branch and never
+//! allocates.
+
+use crate::{meter::Meter, scope::InstrumentationScope};
+
+/// Hands out meters whose instruments discard every measurement.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoopMeterProvider;
+
+impl NoopMeterProvider {
+    pub fn new() -> NoopMeterProvider {
+        NoopMeterProvider
+    }
+
+    pub fn get_meter(&self, name: &str) -> Meter {
+        self.get_meter_with_scope(InstrumentationScope::new(name))
+    }
+
+    pub fn get_meter_with_scope(&self, scope: InstrumentationScope) -> Meter {
+        Meter::noop(scope)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instruments_do_nothing() {
+        let meter = NoopMeterProvider::new().get_meter("lib");
+        assert_eq!(meter.scope().name(), "lib");
+        let counter = meter.create_counter("requests");
+        counter.add(1, &[]);
+        assert!(counter.collect(0).is_empty());
+        assert!(meter.collect(0).metrics.is_empty());
+    }
+}
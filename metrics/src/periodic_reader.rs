@@ -0,0 +1,307 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    aggregation::Aggregation,
+    env_config,
+    error::{MetricsError, MetricsResult},
+    exporter::PushMetricExporter,
+    instrument::InstrumentKind,
+    metric::{ResourceMetrics, Temporality},
+    reader::{MetricProducer, MetricReader},
+};
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(60);
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A reader that collects on a fixed interval and hands each collection to a
+/// [`PushMetricExporter`].
+///
+/// Collection times are anchored to when the reader was registered, so a slow
+/// export delays the next collection only if it overruns the interval. The
+/// background thread starts when the reader is registered with a provider and
+/// stops when the provider shuts down, after exporting one last time.
+///
+/// The interval and timeout default to `OTEL_METRIC_EXPORT_INTERVAL` and
+/// `OTEL_METRIC_EXPORT_TIMEOUT` when those are set.
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use metrics::exporter::StdoutExporter;
+/// use metrics::meter_provider::MeterProvider;
+/// use metrics::periodic_reader::PeriodicReader;
+///
+/// let reader = PeriodicReader::new(StdoutExporter::default())
+///     .with_interval(Duration::from_secs(10));
+/// let provider = MeterProvider::new_with_readers(vec![Box::new(reader)], vec![]).unwrap();
+/// // ...
+/// provider.shutdown().unwrap();
+/// ```
+#[derive(Clone)]
+pub struct PeriodicReader {
+    interval: Duration,
+    timeout: Duration,
+    aggregations: HashMap<InstrumentKind, Aggregation>,
+    exporter: Arc<dyn PushMetricExporter>,
+    worker: Arc<Mutex<Option<Worker>>>,
+    /// Invalid environment settings, reported once the reader is registered.
+    env_errors: Vec<MetricsError>,
+}
+
+struct Worker {
+    messages: Sender<Message>,
+    handle: JoinHandle<()>,
+}
+
+enum Message {
+    Flush(Sender<MetricsResult<()>>),
+    Shutdown(Sender<MetricsResult<()>>),
+}
+
+impl PeriodicReader {
+    /// Creates a reader exporting to `exporter` every 60 seconds, with a
+    /// 30 second export timeout, unless the environment says otherwise.
+    pub fn new(exporter: impl PushMetricExporter) -> PeriodicReader {
+        let mut env_errors = vec![];
+        let interval = env_config::report(&mut env_errors, env_config::export_interval());
+        let timeout = env_config::report(&mut env_errors, env_config::export_timeout());
+        PeriodicReader {
+            interval: interval.unwrap_or(DEFAULT_INTERVAL),
+            timeout: timeout.unwrap_or(DEFAULT_TIMEOUT),
+            aggregations: HashMap::new(),
+            exporter: Arc::new(exporter),
+            worker: Arc::new(Mutex::new(None)),
+            env_errors,
+        }
+    }
+
+    /// Time between the start of two collections.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        if !interval.is_zero() {
+            self.interval = interval;
+        }
+        self
+    }
+
+    /// How long a single export may take.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        if !timeout.is_zero() {
+            self.timeout = timeout;
+        }
+        self
+    }
+
+    /// Aggregate instruments of `kind` with `aggregation` unless a view says
+    /// otherwise.
+    pub fn with_aggregation(mut self, kind: InstrumentKind, aggregation: Aggregation) -> Self {
+        self.aggregations.insert(kind, aggregation);
+        self
+    }
+}
+
+impl MetricReader for PeriodicReader {
+    fn register_producer(&self, producer: MetricProducer) {
+        let mut worker = self.worker.lock().unwrap();
+        if worker.is_some() {
+            return;
+        }
+        for err in &self.env_errors {
+            producer.handle_error(err.clone());
+        }
+        let (messages, receiver) = mpsc::channel();
+        let exporter = self.exporter.clone();
+        let (interval, timeout) = (self.interval, self.timeout);
+        let handle = thread::Builder::new()
+            .name("metrics-periodic-reader".into())
+            .spawn(move || run(producer, exporter, interval, timeout, receiver))
+            .expect("failed to spawn periodic reader thread");
+        *worker = Some(Worker { messages, handle });
+    }
+
+    fn temporality(&self, kind: InstrumentKind) -> Temporality {
+        self.exporter.temporality(kind)
+    }
+
+    fn aggregation(&self, kind: InstrumentKind) -> Aggregation {
+        self.aggregations
+            .get(&kind)
+            .cloned()
+            .unwrap_or(Aggregation::Default)
+    }
+
+    fn collect(&self) -> MetricsResult<ResourceMetrics> {
+        Err(MetricsError::Export(
+            "a periodic reader collects on its own schedule".into(),
+        ))
+    }
+
+    /// Collects and exports immediately, outside the regular schedule.
+    fn force_flush(&self, timeout: Duration) -> MetricsResult<()> {
+        let worker = self.worker.lock().unwrap();
+        let Some(worker) = worker.as_ref() else {
+            return Err(MetricsError::AlreadyShutdown);
+        };
+        let (done, result) = mpsc::channel();
+        worker
+            .messages
+            .send(Message::Flush(done))
+            .map_err(|_| MetricsError::AlreadyShutdown)?;
+        wait(&result, timeout)
+    }
+
+    /// Exports once more, shuts the exporter down and joins the background
+    /// thread. If that takes longer than `timeout` the thread is left to
+    /// finish on its own.
+    fn shutdown(&self, timeout: Duration) -> MetricsResult<()> {
+        let Some(worker) = self.worker.lock().unwrap().take() else {
+            return Ok(());
+        };
+        let (done, result) = mpsc::channel();
+        if worker.messages.send(Message::Shutdown(done)).is_err() {
+            return Err(MetricsError::AlreadyShutdown);
+        }
+        let outcome = wait(&result, timeout);
+        if !matches!(outcome, Err(MetricsError::ExportTimeout(_))) {
+            let _ = worker.handle.join();
+        }
+        outcome
+    }
+}
+
+fn wait(result: &Receiver<MetricsResult<()>>, timeout: Duration) -> MetricsResult<()> {
+    match result.recv_timeout(timeout) {
+        Ok(outcome) => outcome,
+        Err(RecvTimeoutError::Timeout) => Err(MetricsError::ExportTimeout(timeout)),
+        Err(RecvTimeoutError::Disconnected) => Err(MetricsError::AlreadyShutdown),
+    }
+}
+
+fn run(
+    producer: MetricProducer,
+    exporter: Arc<dyn PushMetricExporter>,
+    interval: Duration,
+    timeout: Duration,
+    messages: Receiver<Message>,
+) {
+    let export = || -> MetricsResult<()> {
+        let metrics = producer.produce()?;
+        let started = Instant::now();
+        exporter.export(&metrics, timeout)?;
+        if started.elapsed() > timeout {
+            return Err(MetricsError::ExportTimeout(timeout));
+        }
+        Ok(())
+    };
+
+    let mut next = Instant::now() + interval;
+    loop {
+        match messages.recv_timeout(next.saturating_duration_since(Instant::now())) {
+            Ok(Message::Flush(done)) => {
+                let _ = done.send(export());
+            }
+            Ok(Message::Shutdown(done)) => {
+                let result = export().and(exporter.shutdown());
+                let _ = done.send(result);
+                return;
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                if let Err(err) = export() {
+                    producer.handle_error(err);
+                }
+                // Stay on the original schedule, skipping ticks an export overran.
+                let now = Instant::now();
+                while next <= now {
+                    next += interval;
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meter_provider::MeterProvider;
+    use crate::metric::MetricData;
+
+    #[derive(Clone, Default)]
+    struct RecordingExporter {
+        exports: Arc<Mutex<Vec<ResourceMetrics>>>,
+        shut_down: Arc<Mutex<bool>>,
+    }
+
+    impl PushMetricExporter for RecordingExporter {
+        fn export(&self, metrics: &ResourceMetrics, _timeout: Duration) -> MetricsResult<()> {
+            self.exports.lock().unwrap().push(metrics.clone());
+            Ok(())
+        }
+
+        fn shutdown(&self) -> MetricsResult<()> {
+            *self.shut_down.lock().unwrap() = true;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn exports_on_interval_and_on_shutdown() {
+        let exporter = RecordingExporter::default();
+        let reader = PeriodicReader::new(exporter.clone()).with_interval(Duration::from_millis(20));
+        let provider =
+            MeterProvider::new_with_readers(vec![Box::new(reader.clone())], vec![]).unwrap();
+        let counter = provider.get_meter("meter").create_counter("requests");
+        counter.add(5, &[]);
+
+        // Wait for two interval exports, however slow the machine is.
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while exporter.exports.lock().unwrap().len() < 2 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert!(exporter.exports.lock().unwrap().len() >= 2);
+
+        counter.add(5, &[]);
+        reader.shutdown(Duration::from_secs(1)).unwrap();
+        assert!(*exporter.shut_down.lock().unwrap());
+        let exports = exporter.exports.lock().unwrap();
+        let metric = exports.last().unwrap().metrics().next().unwrap();
+        let MetricData::Sum(sum) = &metric.data else {
+            panic!("expected a sum");
+        };
+        assert_eq!(sum.data_points[0].value, 10);
+    }
+
+    #[test]
+    fn force_flush_exports_immediately() {
+        let exporter = RecordingExporter::default();
+        let reader = PeriodicReader::new(exporter.clone());
+        let provider =
+            MeterProvider::new_with_readers(vec![Box::new(reader.clone())], vec![]).unwrap();
+        provider
+            .get_meter("meter")
+            .create_counter("requests")
+            .add(1, &[]);
+        reader.force_flush(Duration::from_secs(1)).unwrap();
+        assert_eq!(exporter.exports.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn shutdown_joins_thread_once() {
+        let reader = PeriodicReader::new(RecordingExporter::default());
+        let _provider =
+            MeterProvider::new_with_readers(vec![Box::new(reader.clone())], vec![]).unwrap();
+        reader.shutdown(Duration::from_secs(1)).unwrap();
+        assert!(reader.worker.lock().unwrap().is_none());
+        reader.shutdown(Duration::from_secs(1)).unwrap();
+        assert_eq!(
+            reader.force_flush(Duration::from_secs(1)),
+            Err(MetricsError::AlreadyShutdown)
+        );
+    }
+}
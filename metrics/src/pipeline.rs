@@ -0,0 +1,61 @@
+use std::sync::{atomic::AtomicBool, Arc};
+
+use crate::{
+    clock::Clock,
+    counter::CounterInner,
+    error::ErrorHandler,
+    instrument::Instrument,
+    reader::MetricReader,
+    view::{self, View},
+};
+
+/// Provider-wide settings shared by every stream.
+#[derive(Clone)]
+pub(crate) struct StreamContext {
+    pub(crate) clock: Arc<dyn Clock>,
+    pub(crate) error_handler: ErrorHandler,
+    /// Maximum number of points per stream, including the overflow point.
+    pub(crate) cardinality_limit: usize,
+}
+
+/// The views and readers of a provider. Every reader is one pipeline; each
+/// instrument creates its streams once per pipeline.
+pub(crate) struct Pipelines {
+    pub(crate) views: Vec<View>,
+    pub(crate) readers: Vec<Box<dyn MetricReader>>,
+    pub(crate) context: StreamContext,
+    /// Set once the provider shuts down; turns every instrument into a no-op.
+    pub(crate) is_shutdown: Arc<AtomicBool>,
+}
+
+impl Pipelines {
+    pub(crate) fn new(
+        views: Vec<View>,
+        readers: Vec<Box<dyn MetricReader>>,
+        context: StreamContext,
+    ) -> Pipelines {
+        Pipelines {
+            views,
+            readers,
+            context,
+            is_shutdown: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub(crate) fn counter_streams(&self, instrument: &Instrument) -> Vec<Arc<CounterInner>> {
+        let mut streams = vec![];
+        for (pipeline, reader) in self.readers.iter().enumerate() {
+            let default_aggregation = reader.aggregation(instrument.kind);
+            let temporality = reader.temporality(instrument.kind);
+            for stream in view::streams_for(instrument, &self.views, &default_aggregation) {
+                streams.push(Arc::new(CounterInner::new(
+                    pipeline,
+                    stream,
+                    temporality,
+                    self.context.clone(),
+                )));
+            }
+        }
+        streams
+    }
+}
@@ -0,0 +1,375 @@
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use flate2::{write::GzEncoder, Compression};
+
+use crate::{
+    error::{MetricsError, MetricsResult},
+    exporter::prometheus::{self, Format},
+    instrument::InstrumentKind,
+    metric::{ResourceMetrics, Temporality},
+    reader::{ManualReader, MetricProducer, MetricReader},
+};
+
+/// Address Prometheus exporters listen on by convention.
+pub const DEFAULT_BIND_ADDRESS: &str = "0.0.0.0:9464";
+
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_REQUEST_HEAD: u64 = 8 * 1024;
+
+/// A cumulative reader that serves its collections over HTTP.
+///
+/// Every `GET /metrics` collects from the provider and answers with the
+/// OpenMetrics format when the `Accept` header asks for it and the Prometheus
+/// text format otherwise, gzip-compressed when the client accepts it. The
+/// listener is bound when the reader is created and closed when the provider
+/// shuts down.
+///
+/// ```no_run
+/// use metrics::meter_provider::MeterProvider;
+/// use metrics::prometheus_reader::{PrometheusReader, DEFAULT_BIND_ADDRESS};
+///
+/// let reader = PrometheusReader::bind(DEFAULT_BIND_ADDRESS).unwrap();
+/// let provider = MeterProvider::builder().with_reader(reader).build().unwrap();
+/// ```
+#[derive(Clone)]
+pub struct PrometheusReader {
+    reader: ManualReader,
+    local_addr: SocketAddr,
+    listener: Arc<Mutex<Option<TcpListener>>>,
+    server: Arc<Mutex<Option<JoinHandle<()>>>>,
+    is_shutdown: Arc<AtomicBool>,
+}
+
+impl PrometheusReader {
+    /// Binds the HTTP listener to `addr`; use port 0 to pick any free port.
+    pub fn bind(addr: impl ToSocketAddrs) -> MetricsResult<PrometheusReader> {
+        let listener = TcpListener::bind(addr)
+            .map_err(|err| MetricsError::InvalidConfig(format!("cannot bind listener: {}", err)))?;
+        let local_addr = listener
+            .local_addr()
+            .map_err(|err| MetricsError::InvalidConfig(err.to_string()))?;
+        Ok(PrometheusReader {
+            reader: ManualReader::new(Temporality::Cumulative),
+            local_addr,
+            listener: Arc::new(Mutex::new(Some(listener))),
+            server: Arc::new(Mutex::new(None)),
+            is_shutdown: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    /// The address the listener is bound to.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl MetricReader for PrometheusReader {
+    fn register_producer(&self, producer: MetricProducer) {
+        let Some(listener) = self.listener.lock().unwrap().take() else {
+            return;
+        };
+        self.reader.register_producer(producer.clone());
+        let reader = self.reader.clone();
+        let is_shutdown = self.is_shutdown.clone();
+        let handle = thread::Builder::new()
+            .name("metrics-prometheus".into())
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    if is_shutdown.load(Ordering::Acquire) {
+                        return;
+                    }
+                    let Ok(stream) = stream else {
+                        continue;
+                    };
+                    // A slow client must not hold up other scrapes or shutdown.
+                    let reader = reader.clone();
+                    let connection_producer = producer.clone();
+                    let spawned = thread::Builder::new()
+                        .name("metrics-prometheus-conn".into())
+                        .spawn(move || {
+                            if let Err(err) = serve(stream, &reader) {
+                                connection_producer.handle_error(MetricsError::Export(format!(
+                                    "serving /metrics: {}",
+                                    err
+                                )));
+                            }
+                        });
+                    if let Err(err) = spawned {
+                        producer.handle_error(MetricsError::Export(format!(
+                            "serving /metrics: {}",
+                            err
+                        )));
+                    }
+                }
+            })
+            .expect("failed to spawn prometheus server thread");
+        *self.server.lock().unwrap() = Some(handle);
+    }
+
+    fn temporality(&self, _kind: InstrumentKind) -> Temporality {
+        Temporality::Cumulative
+    }
+
+    fn collect(&self) -> MetricsResult<ResourceMetrics> {
+        self.reader.collect()
+    }
+
+    fn force_flush(&self, _timeout: Duration) -> MetricsResult<()> {
+        Ok(())
+    }
+
+    /// Stops accepting scrapes and joins the accept thread. Scrapes already
+    /// in progress finish on their own threads.
+    fn shutdown(&self, timeout: Duration) -> MetricsResult<()> {
+        if self.is_shutdown.swap(true, Ordering::AcqRel) {
+            return Ok(());
+        }
+        self.reader.shutdown(timeout)?;
+        self.listener.lock().unwrap().take();
+        if let Some(handle) = self.server.lock().unwrap().take() {
+            // Wake the accept loop so it sees the shutdown flag.
+            let _ = TcpStream::connect_timeout(&wake_addr(self.local_addr), timeout);
+            let _ = handle.join();
+        }
+        Ok(())
+    }
+}
+
+/// `local_addr` with an unspecified IP replaced by loopback.
+fn wake_addr(local_addr: SocketAddr) -> SocketAddr {
+    let ip = match local_addr.ip() {
+        IpAddr::V4(ip) if ip.is_unspecified() => IpAddr::V4(Ipv4Addr::LOCALHOST),
+        IpAddr::V6(ip) if ip.is_unspecified() => IpAddr::V6(Ipv6Addr::LOCALHOST),
+        ip => ip,
+    };
+    SocketAddr::new(ip, local_addr.port())
+}
+
+struct Request {
+    method: String,
+    path: String,
+    accept: Option<String>,
+    accepts_gzip: bool,
+}
+
+fn serve(mut stream: TcpStream, reader: &ManualReader) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let Some(request) = read_request(&stream)? else {
+        return Ok(());
+    };
+    let path = request.path.split('?').next().unwrap_or_default();
+    if path != "/metrics" {
+        return respond(
+            &mut stream,
+            "404 Not Found",
+            "text/plain",
+            b"not found\n",
+            false,
+        );
+    }
+    if request.method != "GET" {
+        return respond(
+            &mut stream,
+            "405 Method Not Allowed",
+            "text/plain",
+            b"method not allowed\n",
+            false,
+        );
+    }
+    match reader.collect() {
+        Ok(metrics) => {
+            let format = Format::negotiate(request.accept.as_deref());
+            let body = prometheus::encode(&metrics, format);
+            respond(
+                &mut stream,
+                "200 OK",
+                format.content_type(),
+                body.as_bytes(),
+                request.accepts_gzip,
+            )
+        }
+        Err(err) => {
+            let body = format!("{}\n", err);
+            respond(
+                &mut stream,
+                "500 Internal Server Error",
+                "text/plain",
+                body.as_bytes(),
+                false,
+            )
+        }
+    }
+}
+
+/// Reads the request line and headers; the body, if any, is ignored.
+fn read_request(stream: &TcpStream) -> io::Result<Option<Request>> {
+    let mut head = BufReader::new(stream.take(MAX_REQUEST_HEAD));
+    let mut line = String::new();
+    if head.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Ok(None);
+    };
+    let mut request = Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        accept: None,
+        accepts_gzip: false,
+    };
+    loop {
+        line.clear();
+        if head.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("accept") {
+                request.accept = Some(value.trim().to_string());
+            } else if name.trim().eq_ignore_ascii_case("accept-encoding") {
+                request.accepts_gzip = accepts_gzip(value);
+            }
+        }
+    }
+    Ok(Some(request))
+}
+
+/// Whether an `Accept-Encoding` value allows gzip; `gzip;q=0` refuses it.
+fn accepts_gzip(accept_encoding: &str) -> bool {
+    accept_encoding.split(',').any(|encoding| {
+        let mut params = encoding.split(';').map(str::trim);
+        params
+            .next()
+            .is_some_and(|name| name.eq_ignore_ascii_case("gzip"))
+            && !params.any(|param| {
+                param.strip_prefix("q=").and_then(|q| q.parse::<f64>().ok()) == Some(0.0)
+            })
+    })
+}
+
+fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+    gzip: bool,
+) -> io::Result<()> {
+    let compressed;
+    let (body, encoding) = if gzip {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(body)?;
+        compressed = encoder.finish()?;
+        (compressed.as_slice(), "Content-Encoding: gzip\r\n")
+    } else {
+        (body, "")
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len(),
+        encoding
+    )?;
+    stream.write_all(body)?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meter_provider::MeterProvider;
+    use flate2::read::GzDecoder;
+    use std::time::Instant;
+
+    fn get(addr: SocketAddr, path: &str, headers: &str) -> (String, Vec<u8>) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "GET {} HTTP/1.1\r\nHost: localhost\r\n{}\r\n",
+            path, headers
+        )
+        .unwrap();
+        let mut response = vec![];
+        stream.read_to_end(&mut response).unwrap();
+        let split = response
+            .windows(4)
+            .position(|window| window == b"\r\n\r\n")
+            .unwrap();
+        let head = String::from_utf8(response[..split].to_vec()).unwrap();
+        (head, response[split + 4..].to_vec())
+    }
+
+    #[test]
+    fn parses_accept_encoding() {
+        assert!(accepts_gzip("deflate, gzip;q=0.5"));
+        assert!(accepts_gzip("GZIP"));
+        assert!(!accepts_gzip("gzip;q=0"));
+        assert!(!accepts_gzip("gzip; q=0.0, identity"));
+        assert!(!accepts_gzip("identity"));
+    }
+
+    #[test]
+    fn serves_metrics() {
+        let reader = PrometheusReader::bind("127.0.0.1:0").unwrap();
+        let addr = reader.local_addr();
+        let provider = MeterProvider::builder()
+            .with_reader(reader)
+            .build()
+            .unwrap();
+        let counter = provider.get_meter("http").create_counter("requests");
+        counter.add(2, &[]);
+
+        let (head, body) = get(addr, "/metrics", "");
+        assert!(head.starts_with("HTTP/1.1 200 OK"));
+        assert!(head.contains(prometheus::TEXT_CONTENT_TYPE));
+        let body = String::from_utf8(body).unwrap();
+        assert!(body.contains("requests_total{otel_scope_name=\"http\"} 2\n"));
+
+        // cumulative: a second scrape still sees the first measurement
+        counter.add(1, &[]);
+        let (head, body) = get(addr, "/metrics?x=1", "Accept-Encoding: br, gzip;q=0.8\r\n");
+        assert!(head.contains("Content-Encoding: gzip"));
+        let mut decoded = String::new();
+        GzDecoder::new(body.as_slice())
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert!(decoded.contains("requests_total{otel_scope_name=\"http\"} 3\n"));
+
+        let (head, body) = get(addr, "/metrics", "Accept: application/openmetrics-text\r\n");
+        assert!(head.contains(prometheus::OPENMETRICS_CONTENT_TYPE));
+        assert!(String::from_utf8(body).unwrap().ends_with("# EOF\n"));
+
+        let (head, _) = get(addr, "/other", "");
+        assert!(head.starts_with("HTTP/1.1 404"));
+
+        provider.shutdown().unwrap();
+        assert!(TcpStream::connect(addr).is_err());
+    }
+
+    #[test]
+    fn idle_connection_does_not_block_scrapes_or_shutdown() {
+        let reader = PrometheusReader::bind("127.0.0.1:0").unwrap();
+        let addr = reader.local_addr();
+        let provider = MeterProvider::builder()
+            .with_reader(reader)
+            .build()
+            .unwrap();
+        let _idle = TcpStream::connect(addr).unwrap();
+
+        let start = Instant::now();
+        let (head, _) = get(addr, "/metrics", "");
+        assert!(head.starts_with("HTTP/1.1 200 OK"));
+        provider.shutdown().unwrap();
+        assert!(start.elapsed() < READ_TIMEOUT);
+    }
+}
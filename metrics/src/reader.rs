@@ -0,0 +1,143 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, Weak,
+    },
+    time::Duration,
+};
+
+use crate::{
+    aggregation::Aggregation,
+    error::{ErrorHandler, MetricsError, MetricsResult},
+    instrument::InstrumentKind,
+    meter_provider::MeterProviderInner,
+    metric::{ResourceMetrics, Temporality},
+};
+
+/// Pulls metrics out of a `MeterProvider`.
+///
+/// Each reader registered with a provider gets its own copy of every metric
+/// stream, so readers never observe each other's collections. Temporality and
+/// default aggregation are asked once per instrument, when it is created.
+pub trait MetricReader: Send + Sync {
+    /// Called by the provider when the reader is registered.
+    fn register_producer(&self, producer: MetricProducer);
+
+    /// Temporality of the streams this reader collects for `kind`.
+    fn temporality(&self, kind: InstrumentKind) -> Temporality;
+
+    /// Aggregation used for `kind` when no view sets one.
+    fn aggregation(&self, _kind: InstrumentKind) -> Aggregation {
+        Aggregation::Default
+    }
+
+    /// Collects the current state of this reader's streams.
+    fn collect(&self) -> MetricsResult<ResourceMetrics>;
+
+    /// Exports anything pending, waiting at most `timeout`.
+    fn force_flush(&self, timeout: Duration) -> MetricsResult<()>;
+
+    /// Flushes and releases the reader, waiting at most `timeout`. Later calls
+    /// have no effect.
+    fn shutdown(&self, timeout: Duration) -> MetricsResult<()>;
+}
+
+/// Handle a reader uses to collect its streams from the provider it was
+/// registered with.
+#[derive(Clone)]
+pub struct MetricProducer {
+    provider: Weak<MeterProviderInner>,
+    pipeline: usize,
+    error_handler: ErrorHandler,
+}
+
+impl MetricProducer {
+    pub(crate) fn new(
+        provider: Weak<MeterProviderInner>,
+        pipeline: usize,
+        error_handler: ErrorHandler,
+    ) -> MetricProducer {
+        MetricProducer {
+            provider,
+            pipeline,
+            error_handler,
+        }
+    }
+
+    /// Passes `err` to the provider's error handler.
+    pub fn handle_error(&self, err: MetricsError) {
+        (self.error_handler)(err)
+    }
+
+    pub fn produce(&self) -> MetricsResult<ResourceMetrics> {
+        let provider = self
+            .provider
+            .upgrade()
+            .ok_or(MetricsError::ReaderNotRegistered)?;
+        Ok(provider.collect(self.pipeline))
+    }
+}
+
+/// A reader that collects only when `collect` is called.
+#[derive(Clone)]
+pub struct ManualReader {
+    temporality: Temporality,
+    aggregations: HashMap<InstrumentKind, Aggregation>,
+    producer: Arc<Mutex<Option<MetricProducer>>>,
+    is_shutdown: Arc<AtomicBool>,
+}
+
+impl ManualReader {
+    pub fn new(temporality: Temporality) -> ManualReader {
+        ManualReader {
+            temporality,
+            aggregations: HashMap::new(),
+            producer: Arc::new(Mutex::new(None)),
+            is_shutdown: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Aggregate instruments of `kind` with `aggregation` unless a view says
+    /// otherwise.
+    pub fn with_aggregation(mut self, kind: InstrumentKind, aggregation: Aggregation) -> Self {
+        self.aggregations.insert(kind, aggregation);
+        self
+    }
+}
+
+impl MetricReader for ManualReader {
+    fn register_producer(&self, producer: MetricProducer) {
+        *self.producer.lock().unwrap() = Some(producer);
+    }
+
+    fn temporality(&self, _kind: InstrumentKind) -> Temporality {
+        self.temporality
+    }
+
+    fn aggregation(&self, kind: InstrumentKind) -> Aggregation {
+        self.aggregations
+            .get(&kind)
+            .cloned()
+            .unwrap_or(Aggregation::Default)
+    }
+
+    fn collect(&self) -> MetricsResult<ResourceMetrics> {
+        if self.is_shutdown.load(Ordering::Acquire) {
+            return Err(MetricsError::AlreadyShutdown);
+        }
+        match &*self.producer.lock().unwrap() {
+            Some(producer) => producer.produce(),
+            None => Err(MetricsError::ReaderNotRegistered),
+        }
+    }
+
+    fn force_flush(&self, _timeout: Duration) -> MetricsResult<()> {
+        Ok(())
+    }
+
+    fn shutdown(&self, _timeout: Duration) -> MetricsResult<()> {
+        self.is_shutdown.store(true, Ordering::Release);
+        Ok(())
+    }
+}
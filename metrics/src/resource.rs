@@ -0,0 +1,275 @@
+use std::env;
+
+use crate::common::{Key, KeyValue, Value};
+
+const SERVICE_NAME: &str = "service.name";
+
+/// Describes the entity producing metrics, such as a service on a host.
+///
+/// Keys are unique; when the same key is given more than once the last value
+/// wins. `Resource::default()` holds the SDK's own attributes,
+/// `service.name=unknown_service` and whatever [`EnvResourceDetector`] finds;
+/// use [`Resource::empty`] to start from nothing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Resource {
+    attributes: Vec<KeyValue>,
+}
+
+impl Default for Resource {
+    fn default() -> Self {
+        Resource::new([
+            KeyValue::new(SERVICE_NAME, "unknown_service"),
+            KeyValue::new("telemetry.sdk.name", "metrics-mini"),
+            KeyValue::new("telemetry.sdk.language", "rust"),
+            KeyValue::new("telemetry.sdk.version", env!("CARGO_PKG_VERSION")),
+        ])
+        .merge(&EnvResourceDetector.detect())
+    }
+}
+
+impl Resource {
+    pub fn new(attributes: impl IntoIterator<Item = KeyValue>) -> Resource {
+        let mut resource = Resource::empty();
+        for kv in attributes {
+            resource.insert(kv);
+        }
+        resource
+    }
+
+    pub fn empty() -> Resource {
+        Resource { attributes: vec![] }
+    }
+
+    /// Runs `detectors` in order and merges their results; later detectors
+    /// override earlier ones.
+    pub fn from_detectors(detectors: &[&dyn ResourceDetector]) -> Resource {
+        detectors
+            .iter()
+            .fold(Resource::empty(), |resource, detector| {
+                resource.merge(&detector.detect())
+            })
+    }
+
+    /// Returns a resource with the attributes of both; values from `other`
+    /// win when a key is present in both.
+    pub fn merge(&self, other: &Resource) -> Resource {
+        let mut merged = self.clone();
+        for kv in other.iter() {
+            merged.insert(kv.clone());
+        }
+        merged
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.attributes
+            .binary_search_by(|kv| kv.key.as_str().cmp(key))
+            .ok()
+            .map(|index| &self.attributes[index].value)
+    }
+
+    /// Attributes sorted by key.
+    pub fn iter(&self) -> impl Iterator<Item = &KeyValue> {
+        self.attributes.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.attributes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.attributes.is_empty()
+    }
+
+    fn insert(&mut self, kv: KeyValue) {
+        match self
+            .attributes
+            .binary_search_by(|existing| existing.key.cmp(&kv.key))
+        {
+            Ok(index) => self.attributes[index] = kv,
+            Err(index) => self.attributes.insert(index, kv),
+        }
+    }
+}
+
+/// Discovers resource attributes from the environment the process runs in.
+pub trait ResourceDetector {
+    fn detect(&self) -> Resource;
+}
+
+/// Reads `OTEL_RESOURCE_ATTRIBUTES` (`key1=value1,key2=value2`, values
+/// percent-encoded) and `OTEL_SERVICE_NAME`, which takes precedence over a
+/// `service.name` given in the former.
+///
+/// A malformed `OTEL_RESOURCE_ATTRIBUTES` is ignored as a whole.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EnvResourceDetector;
+
+impl ResourceDetector for EnvResourceDetector {
+    fn detect(&self) -> Resource {
+        from_env_values(
+            env::var("OTEL_RESOURCE_ATTRIBUTES").ok().as_deref(),
+            env::var("OTEL_SERVICE_NAME").ok().as_deref(),
+        )
+    }
+}
+
+fn from_env_values(attributes: Option<&str>, service_name: Option<&str>) -> Resource {
+    let mut resource = attributes
+        .and_then(parse_attributes)
+        .map(Resource::new)
+        .unwrap_or_else(Resource::empty);
+    if let Some(service_name) = service_name.map(str::trim).filter(|name| !name.is_empty()) {
+        resource.insert(KeyValue::new(SERVICE_NAME, service_name.to_string()));
+    }
+    resource
+}
+
+fn parse_attributes(value: &str) -> Option<Vec<KeyValue>> {
+    let mut attributes = vec![];
+    for pair in value.split(',').filter(|pair| !pair.trim().is_empty()) {
+        let (key, value) = pair.split_once('=')?;
+        let key = key.trim();
+        if key.is_empty() {
+            return None;
+        }
+        let value = percent_decode(value.trim())?;
+        attributes.push(KeyValue::new(Key::from(key.to_string()), value));
+    }
+    Some(attributes)
+}
+
+pub(crate) fn percent_decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = value.get(i + 1..i + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// Describes the running process: `process.pid`, `process.executable.name`,
+/// `process.executable.path`, `process.command_line` and the runtime.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ProcessResourceDetector;
+
+impl ResourceDetector for ProcessResourceDetector {
+    fn detect(&self) -> Resource {
+        let mut attributes = vec![
+            KeyValue::new("process.pid", std::process::id() as i64),
+            KeyValue::new("process.runtime.name", "rust"),
+            KeyValue::new(
+                "process.command_line",
+                env::args().collect::<Vec<_>>().join(" "),
+            ),
+        ];
+        if let Ok(path) = env::current_exe() {
+            if let Some(name) = path.file_name() {
+                attributes.push(KeyValue::new(
+                    "process.executable.name",
+                    name.to_string_lossy().into_owned(),
+                ));
+            }
+            attributes.push(KeyValue::new(
+                "process.executable.path",
+                path.to_string_lossy().into_owned(),
+            ));
+        }
+        Resource::new(attributes)
+    }
+}
+
+/// Describes the host: `host.name`, `host.arch` and `os.type`.
+///
+/// The host name comes from the kernel where it is exposed through `/proc`,
+/// falling back to the `HOSTNAME`/`COMPUTERNAME` environment variables.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HostResourceDetector;
+
+impl ResourceDetector for HostResourceDetector {
+    fn detect(&self) -> Resource {
+        let mut attributes = vec![
+            KeyValue::new("host.arch", env::consts::ARCH),
+            KeyValue::new("os.type", env::consts::OS),
+        ];
+        if let Some(name) = host_name() {
+            attributes.push(KeyValue::new("host.name", name));
+        }
+        Resource::new(attributes)
+    }
+}
+
+fn host_name() -> Option<String> {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .ok()
+        .or_else(|| env::var("HOSTNAME").ok())
+        .or_else(|| env::var("COMPUTERNAME").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn last_value_wins_and_keys_are_sorted() {
+        let resource = Resource::new([
+            KeyValue::new("service.version", "1.0"),
+            KeyValue::new("service.name", "a"),
+            KeyValue::new("service.name", "b"),
+        ]);
+        assert_eq!(resource.len(), 2);
+        assert_eq!(resource.get("service.name"), Some(&Value::from("b")));
+        let keys: Vec<&str> = resource.iter().map(|kv| kv.key.as_str()).collect();
+        assert_eq!(keys, vec!["service.name", "service.version"]);
+    }
+
+    #[test]
+    fn merge_prefers_other() {
+        let base = Resource::new([KeyValue::new("a", "1"), KeyValue::new("b", "1")]);
+        let merged = base.merge(&Resource::new([KeyValue::new("b", "2")]));
+        assert_eq!(merged.get("a"), Some(&Value::from("1")));
+        assert_eq!(merged.get("b"), Some(&Value::from("2")));
+    }
+
+    #[test]
+    fn env_values() {
+        let resource = from_env_values(
+            Some("service.name=from-attrs, deployment.environment=prod,team=a%2Cb"),
+            Some("checkout"),
+        );
+        assert_eq!(resource.get("service.name"), Some(&Value::from("checkout")));
+        assert_eq!(
+            resource.get("deployment.environment"),
+            Some(&Value::from("prod"))
+        );
+        assert_eq!(resource.get("team"), Some(&Value::from("a,b")));
+
+        // one malformed pair discards the whole variable
+        assert!(from_env_values(Some("a=1,b"), None).is_empty());
+        assert!(from_env_values(Some("a=%zz"), None).is_empty());
+        assert!(from_env_values(None, Some(" ")).is_empty());
+    }
+
+    #[test]
+    fn process_and_host_detectors() {
+        let resource = Resource::from_detectors(&[&ProcessResourceDetector, &HostResourceDetector]);
+        assert_eq!(
+            resource.get("process.pid"),
+            Some(&Value::I64(std::process::id() as i64))
+        );
+        assert!(resource.get("process.executable.name").is_some());
+        assert_eq!(
+            resource.get("host.arch"),
+            Some(&Value::from(env::consts::ARCH))
+        );
+    }
+}
@@ -0,0 +1,55 @@
+use crate::common::KeyValue;
+
+/// Identifies the library that created a meter.
+///
+/// Meters are keyed by the whole scope: asking a provider for the same name
+/// with a different version, schema URL or attributes yields a separate meter.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct InstrumentationScope {
+    name: String,
+    version: Option<String>,
+    schema_url: Option<String>,
+    attributes: Vec<KeyValue>,
+}
+
+impl InstrumentationScope {
+    pub fn new(name: &str) -> InstrumentationScope {
+        InstrumentationScope {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_version(mut self, version: &str) -> Self {
+        self.version = Some(version.to_string());
+        self
+    }
+
+    pub fn with_schema_url(mut self, schema_url: &str) -> Self {
+        self.schema_url = Some(schema_url.to_string());
+        self
+    }
+
+    /// Attributes describing the scope. Their order does not affect identity.
+    pub fn with_attributes(mut self, attributes: impl IntoIterator<Item = KeyValue>) -> Self {
+        self.attributes = attributes.into_iter().collect();
+        self.attributes.sort();
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    pub fn schema_url(&self) -> Option<&str> {
+        self.schema_url.as_deref()
+    }
+
+    pub fn attributes(&self) -> &[KeyValue] {
+        &self.attributes
+    }
+}
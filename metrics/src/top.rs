@@ -0,0 +1,811 @@
+//! A live terminal dashboard over collected metrics, as run by the
+//! `metrics-top` binary.
+//!
+//! [`Top`] either collects from a cumulative [`ManualReader`] registered with
+//! an in-process `MeterProvider`, or scrapes a Prometheus text endpoint such
+//! as the one a [`PrometheusReader`](crate::prometheus_reader::PrometheusReader)
+//! serves. Each series shows its latest value, its change per second since
+//! the previous refresh with a sparkline of recent rates and, for histograms,
+//! the 50th, 90th and 99th percentiles estimated from the buckets filled since
+//! the previous refresh.
+//!
+//! Keys: `s` cycles the sort column, `r` reverses it, `/` edits the filter,
+//! `Esc` clears it and `q` quits.
+
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::{Attribute, Print, SetAttribute},
+    terminal::{self, ClearType},
+};
+
+use crate::{
+    common::KeyValue,
+    error::MetricsResult,
+    exporter::http_client::{self, Endpoint},
+    metric::{MetricData, ResourceMetrics},
+    reader::{ManualReader, MetricReader},
+};
+
+/// Rates kept per series for its sparkline.
+const HISTORY: usize = 60;
+
+/// Watches a provider or a scrape endpoint in the terminal.
+///
+/// ```no_run
+/// use metrics::meter_provider::MeterProvider;
+/// use metrics::metric::Temporality;
+/// use metrics::reader::ManualReader;
+/// use metrics::top::Top;
+///
+/// let reader = ManualReader::new(Temporality::Cumulative);
+/// let provider = MeterProvider::builder()
+///     .with_reader(reader.clone())
+///     .build()
+///     .unwrap();
+/// // Record from other threads, then take over the terminal until `q`.
+/// Top::attach(reader).run().unwrap();
+/// ```
+pub struct Top {
+    source: Source,
+    interval: Duration,
+    dashboard: Dashboard,
+}
+
+enum Source {
+    Reader(ManualReader),
+    Scrape(Endpoint),
+}
+
+impl Top {
+    /// Collects from `reader`, which must be cumulative and registered with
+    /// the provider to watch.
+    pub fn attach(reader: ManualReader) -> Top {
+        Top {
+            source: Source::Reader(reader),
+            interval: Duration::from_secs(1),
+            dashboard: Dashboard::new("in-process"),
+        }
+    }
+
+    /// Scrapes the Prometheus text endpoint at `url`, for example
+    /// `http://localhost:9464/metrics`.
+    pub fn scrape(url: &str) -> MetricsResult<Top> {
+        Ok(Top {
+            source: Source::Scrape(Endpoint::parse(url)?),
+            interval: Duration::from_secs(1),
+            dashboard: Dashboard::new(url),
+        })
+    }
+
+    /// How often to collect. Defaults to one second.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Takes over the terminal until `q` or `Ctrl-C` is pressed.
+    pub fn run(mut self) -> io::Result<()> {
+        let mut stdout = io::stdout();
+        terminal::enable_raw_mode()?;
+        let _restore = RestoreTerminal;
+        execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+
+        let mut last_collect: Option<Instant> = None;
+        let mut next_collect = Instant::now();
+        loop {
+            let now = Instant::now();
+            if now >= next_collect {
+                match self.collect() {
+                    Ok(samples) => {
+                        let elapsed = last_collect.map(|last| now - last);
+                        self.dashboard.update(samples, elapsed);
+                        self.dashboard.error = None;
+                        last_collect = Some(now);
+                    }
+                    Err(err) => self.dashboard.error = Some(err),
+                }
+                next_collect = now + self.interval;
+            }
+            self.draw(&mut stdout)?;
+            if event::poll(next_collect.saturating_duration_since(Instant::now()))? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind != KeyEventKind::Release && !self.dashboard.handle_key(key) {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+
+    fn collect(&self) -> Result<Vec<Sample>, String> {
+        match &self.source {
+            Source::Reader(reader) => {
+                let metrics = reader.collect().map_err(|err| err.to_string())?;
+                Ok(samples(&metrics))
+            }
+            Source::Scrape(endpoint) => {
+                let deadline = Instant::now() + self.interval.max(Duration::from_secs(1));
+                let response = http_client::get(endpoint, &[("Accept", "text/plain")], deadline)
+                    .map_err(|err| format!("scrape failed: {}", err))?;
+                if response.status != 200 {
+                    return Err(format!("scrape failed: HTTP {}", response.status));
+                }
+                Ok(parse_exposition(&String::from_utf8_lossy(&response.body)))
+            }
+        }
+    }
+
+    fn draw(&self, out: &mut impl Write) -> io::Result<()> {
+        let (width, height) = terminal::size()?;
+        let lines = self.dashboard.render(width.into(), height.into());
+        for (row, line) in lines.iter().enumerate() {
+            queue!(out, cursor::MoveTo(0, row as u16))?;
+            if row == 1 {
+                queue!(out, SetAttribute(Attribute::Reverse), Print(line))?;
+                queue!(out, SetAttribute(Attribute::Reset))?;
+            } else {
+                queue!(out, Print(line))?;
+            }
+            queue!(out, terminal::Clear(ClearType::UntilNewLine))?;
+        }
+        queue!(out, terminal::Clear(ClearType::FromCursorDown))?;
+        out.flush()
+    }
+}
+
+/// Leaves the alternate screen and raw mode, also when `run` fails.
+struct RestoreTerminal;
+
+impl Drop for RestoreTerminal {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Meter, instrument and attributes of a series.
+type SeriesKey = (String, String, String);
+
+/// One series of a collection.
+#[derive(Debug, PartialEq)]
+struct Sample {
+    meter: String,
+    name: String,
+    unit: String,
+    /// Sorted `k=v` pairs joined by `,`.
+    attributes: String,
+    /// The value, or the count of a histogram.
+    value: f64,
+    histogram: Option<Buckets>,
+}
+
+/// Explicit histogram buckets; `counts` has one more entry than `bounds`.
+#[derive(Clone, Debug, PartialEq)]
+struct Buckets {
+    bounds: Vec<f64>,
+    counts: Vec<u64>,
+}
+
+impl Buckets {
+    /// Observations recorded since `earlier`, if both have the same bounds.
+    fn since(&self, earlier: &Buckets) -> Option<Buckets> {
+        if self.bounds != earlier.bounds || self.counts.len() != earlier.counts.len() {
+            return None;
+        }
+        let counts = self
+            .counts
+            .iter()
+            .zip(&earlier.counts)
+            .map(|(now, before)| now.saturating_sub(*before))
+            .collect();
+        Some(Buckets {
+            bounds: self.bounds.clone(),
+            counts,
+        })
+    }
+
+    /// Estimates the `q` quantile by interpolating linearly within the
+    /// bucket it falls in. The first bucket is taken to start at 0 and the
+    /// overflow bucket to end at the last bound.
+    fn quantile(&self, q: f64) -> Option<f64> {
+        let total: u64 = self.counts.iter().sum();
+        if total == 0 || self.bounds.is_empty() {
+            return None;
+        }
+        let rank = q * total as f64;
+        let mut seen = 0;
+        for (i, &count) in self.counts.iter().enumerate() {
+            if count > 0 && (seen + count) as f64 >= rank {
+                let Some(&upper) = self.bounds.get(i) else {
+                    break;
+                };
+                let lower = match i {
+                    0 => upper.min(0.0),
+                    _ => self.bounds[i - 1],
+                };
+                let fraction = (rank - seen as f64) / count as f64;
+                return Some(lower + (upper - lower) * fraction.clamp(0.0, 1.0));
+            }
+            seen += count;
+        }
+        self.bounds.last().copied()
+    }
+}
+
+fn attribute_string<'a>(attributes: impl IntoIterator<Item = (&'a str, String)>) -> String {
+    let mut pairs: Vec<_> = attributes
+        .into_iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect();
+    pairs.sort();
+    pairs.join(",")
+}
+
+/// Flattens a collection into samples.
+fn samples(metrics: &ResourceMetrics) -> Vec<Sample> {
+    let mut samples = vec![];
+    for scope_metrics in &metrics.scope_metrics {
+        for metric in &scope_metrics.metrics {
+            let mut push = |attributes: &[KeyValue], value, histogram| {
+                let attributes = attributes
+                    .iter()
+                    .map(|kv| (kv.key.as_str(), kv.value.to_string()));
+                samples.push(Sample {
+                    meter: scope_metrics.scope.name().to_string(),
+                    name: metric.name.clone(),
+                    unit: metric.unit.clone(),
+                    attributes: attribute_string(attributes),
+                    value,
+                    histogram,
+                })
+            };
+            match &metric.data {
+                MetricData::Sum(sum) => {
+                    for point in &sum.data_points {
+                        push(&point.attributes, point.value as f64, None);
+                    }
+                }
+                MetricData::Gauge(gauge) => {
+                    for point in &gauge.data_points {
+                        push(&point.attributes, point.value as f64, None);
+                    }
+                }
+                MetricData::Histogram(histogram) => {
+                    for point in &histogram.data_points {
+                        let buckets = Buckets {
+                            bounds: point.bounds.clone(),
+                            counts: point.bucket_counts.clone(),
+                        };
+                        push(&point.attributes, point.count as f64, Some(buckets));
+                    }
+                }
+                MetricData::ExponentialHistogram(histogram) => {
+                    for point in &histogram.data_points {
+                        push(&point.attributes, point.count as f64, None);
+                    }
+                }
+            }
+        }
+    }
+    samples
+}
+
+/// Reads the samples of a Prometheus text exposition.
+///
+/// Counters are named without `_total` and histograms are put back together
+/// from their `_bucket` and `_count` series. The meter comes from the
+/// `otel_scope_name` label; `target_info` and `_created` series are skipped.
+fn parse_exposition(text: &str) -> Vec<Sample> {
+    let mut types: HashMap<&str, &str> = HashMap::new();
+    let mut samples = vec![];
+    // Cumulative buckets by `le`, and the count, of each histogram series.
+    let mut histograms: BTreeMap<SeriesKey, (Vec<(f64, f64)>, f64)> = BTreeMap::new();
+    for line in text.lines() {
+        if let Some(family) = line.strip_prefix("# TYPE ") {
+            let mut parts = family.split_whitespace();
+            if let (Some(name), Some(kind)) = (parts.next(), parts.next()) {
+                types.insert(name, kind);
+            }
+            continue;
+        }
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+        let Some((name, mut labels, value)) = parse_sample(line) else {
+            continue;
+        };
+        if name == "target_info" || name.ends_with("_created") {
+            continue;
+        }
+        let meter = labels.remove("otel_scope_name").unwrap_or_default();
+        labels.remove("otel_scope_version");
+        let histogram_family = |suffix: &str| {
+            name.strip_suffix(suffix)
+                .filter(|family| types.get(family) == Some(&"histogram"))
+        };
+        if let Some(family) = histogram_family("_bucket") {
+            let le = labels.remove("le").and_then(|le| le.parse::<f64>().ok());
+            let key = (meter, family.to_string(), attribute_string(labels));
+            if let Some(le) = le {
+                histograms.entry(key).or_default().0.push((le, value));
+            }
+        } else if let Some(family) = histogram_family("_count") {
+            let key = (meter, family.to_string(), attribute_string(labels));
+            histograms.entry(key).or_default().1 = value;
+        } else if histogram_family("_sum").is_none() {
+            // The text format declares counters with `_total`, OpenMetrics without.
+            let is_counter = |family: &str| types.get(family) == Some(&"counter");
+            let name = name
+                .strip_suffix("_total")
+                .filter(|family| is_counter(family) || is_counter(name))
+                .unwrap_or(name);
+            samples.push(Sample {
+                meter,
+                name: name.to_string(),
+                unit: String::new(),
+                attributes: attribute_string(labels),
+                value,
+                histogram: None,
+            });
+        }
+    }
+    for ((meter, name, attributes), (mut buckets, count)) in histograms {
+        buckets.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let bounds = buckets
+            .iter()
+            .map(|(le, _)| *le)
+            .filter(|le| le.is_finite())
+            .collect();
+        let mut previous = 0.0;
+        let mut counts: Vec<u64> = buckets
+            .iter()
+            .map(|(_, cumulative)| {
+                let count = (cumulative - previous).max(0.0) as u64;
+                previous = *cumulative;
+                count
+            })
+            .collect();
+        if buckets.last().is_none_or(|(le, _)| le.is_finite()) {
+            counts.push((count - previous).max(0.0) as u64);
+        }
+        samples.push(Sample {
+            meter,
+            name,
+            unit: String::new(),
+            attributes,
+            value: count,
+            histogram: Some(Buckets { bounds, counts }),
+        });
+    }
+    samples
+}
+
+/// Splits `name{key="value",...} value [timestamp]`.
+fn parse_sample(line: &str) -> Option<(&str, BTreeMap<&str, String>, f64)> {
+    let name_end = line.find(['{', ' ']).unwrap_or(line.len());
+    let (name, mut rest) = line.split_at(name_end);
+    let mut labels = BTreeMap::new();
+    if let Some(after_brace) = rest.strip_prefix('{') {
+        rest = after_brace;
+        loop {
+            rest = rest.trim_start_matches([',', ' ']);
+            if let Some(after) = rest.strip_prefix('}') {
+                rest = after;
+                break;
+            }
+            let (key, after_key) = rest.split_once("=\"")?;
+            let mut value = String::new();
+            let mut chars = after_key.char_indices();
+            let end = loop {
+                match chars.next()? {
+                    (i, '"') => break i,
+                    (_, '\\') => match chars.next()?.1 {
+                        'n' => value.push('\n'),
+                        c => value.push(c),
+                    },
+                    (_, c) => value.push(c),
+                }
+            };
+            labels.insert(key.trim(), value);
+            rest = &after_key[end + 1..];
+        }
+    }
+    let value = rest.split_whitespace().next()?.parse().ok()?;
+    Some((name, labels, value))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SortKey {
+    Name,
+    Value,
+    Rate,
+}
+
+impl SortKey {
+    fn next(self) -> SortKey {
+        match self {
+            SortKey::Name => SortKey::Value,
+            SortKey::Value => SortKey::Rate,
+            SortKey::Rate => SortKey::Name,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            SortKey::Name => "name",
+            SortKey::Value => "value",
+            SortKey::Rate => "rate",
+        }
+    }
+}
+
+struct Row {
+    unit: String,
+    value: f64,
+    /// Change per second over the last refresh, newest last.
+    rates: VecDeque<f64>,
+    histogram: Option<Buckets>,
+    /// Buckets filled since the previous refresh.
+    recent: Option<Buckets>,
+}
+
+/// What the dashboard shows, apart from the terminal.
+struct Dashboard {
+    source: String,
+    rows: BTreeMap<SeriesKey, Row>,
+    sort: SortKey,
+    reverse: bool,
+    filter: String,
+    editing_filter: bool,
+    error: Option<String>,
+}
+
+impl Dashboard {
+    fn new(source: &str) -> Dashboard {
+        Dashboard {
+            source: source.to_string(),
+            rows: BTreeMap::new(),
+            sort: SortKey::Name,
+            reverse: false,
+            filter: String::new(),
+            editing_filter: false,
+            error: None,
+        }
+    }
+
+    /// Replaces the rows with a new collection taken `elapsed` after the
+    /// previous one. Series missing from it are dropped.
+    fn update(&mut self, samples: Vec<Sample>, elapsed: Option<Duration>) {
+        let seconds = elapsed.map(|elapsed| elapsed.as_secs_f64());
+        let mut rows = BTreeMap::new();
+        for sample in samples {
+            let key = (sample.meter, sample.name, sample.attributes);
+            let previous = self.rows.remove(&key);
+            let mut rates = VecDeque::new();
+            let mut recent = None;
+            if let Some(previous) = previous {
+                rates = previous.rates;
+                if let Some(seconds) = seconds.filter(|seconds| *seconds > 0.0) {
+                    rates.push_back((sample.value - previous.value) / seconds);
+                    if rates.len() > HISTORY {
+                        rates.pop_front();
+                    }
+                }
+                if let (Some(now), Some(before)) = (&sample.histogram, &previous.histogram) {
+                    recent = now.since(before);
+                }
+            }
+            let row = Row {
+                unit: sample.unit,
+                value: sample.value,
+                rates,
+                histogram: sample.histogram,
+                recent,
+            };
+            rows.insert(key, row);
+        }
+        self.rows = rows;
+    }
+
+    /// Applies a key press; returns `false` to quit.
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return false;
+        }
+        if self.editing_filter {
+            match key.code {
+                KeyCode::Char(c) => self.filter.push(c),
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Enter => self.editing_filter = false,
+                KeyCode::Esc => {
+                    self.filter.clear();
+                    self.editing_filter = false;
+                }
+                _ => {}
+            }
+            return true;
+        }
+        match key.code {
+            KeyCode::Char('q') => return false,
+            KeyCode::Char('s') => self.sort = self.sort.next(),
+            KeyCode::Char('r') => self.reverse = !self.reverse,
+            KeyCode::Char('/') => self.editing_filter = true,
+            KeyCode::Esc => self.filter.clear(),
+            _ => {}
+        }
+        true
+    }
+
+    /// Rows matching the filter, in display order. Names sort ascending,
+    /// values and rates descending, unless reversed.
+    fn visible(&self) -> Vec<(&SeriesKey, &Row)> {
+        let filter = self.filter.to_lowercase();
+        let mut rows: Vec<_> = self
+            .rows
+            .iter()
+            .filter(|((meter, name, attributes), _)| {
+                [meter, name, attributes]
+                    .iter()
+                    .any(|field| field.to_lowercase().contains(&filter))
+            })
+            .collect();
+        match self.sort {
+            SortKey::Name => {}
+            SortKey::Value => rows.sort_by(|a, b| b.1.value.total_cmp(&a.1.value)),
+            SortKey::Rate => {
+                let rate = |row: &Row| row.rates.back().copied().unwrap_or(f64::NEG_INFINITY);
+                rows.sort_by(|a, b| rate(b.1).total_cmp(&rate(a.1)));
+            }
+        }
+        if self.reverse {
+            rows.reverse();
+        }
+        rows
+    }
+
+    /// The screen as lines at most `width` characters wide: a status line,
+    /// the column headers, as many rows as fit and the key help.
+    fn render(&self, width: usize, height: usize) -> Vec<String> {
+        let rows = self.visible();
+        let mut status = format!(
+            "metrics-top  {}  {}/{} series  sort: {}{}",
+            self.source,
+            rows.len(),
+            self.rows.len(),
+            self.sort.label(),
+            if self.reverse { " (reversed)" } else { "" },
+        );
+        if self.editing_filter || !self.filter.is_empty() {
+            status.push_str(&format!("  filter: {}", self.filter));
+            if self.editing_filter {
+                status.push('_');
+            }
+        }
+        if let Some(error) = &self.error {
+            status.push_str(&format!("  error: {}", error));
+        }
+
+        let mut table = vec![[
+            "METER".to_string(),
+            "INSTRUMENT".to_string(),
+            "ATTRIBUTES".to_string(),
+            "VALUE".to_string(),
+            "RATE/s".to_string(),
+            "TREND".to_string(),
+            "P50".to_string(),
+            "P90".to_string(),
+            "P99".to_string(),
+        ]];
+        let room = height.saturating_sub(3);
+        for ((meter, name, attributes), row) in rows.into_iter().take(room) {
+            let instrument = match row.unit.as_str() {
+                "" => name.clone(),
+                unit => format!("{} ({})", name, unit),
+            };
+            let buckets = row
+                .recent
+                .as_ref()
+                .filter(|recent| recent.counts.iter().any(|count| *count > 0))
+                .or(row.histogram.as_ref());
+            let quantile = |q| {
+                buckets
+                    .and_then(|buckets| buckets.quantile(q))
+                    .map_or_else(String::new, number)
+            };
+            table.push([
+                meter.clone(),
+                instrument,
+                attributes.clone(),
+                number(row.value),
+                row.rates
+                    .back()
+                    .map_or_else(String::new, |rate| number(*rate)),
+                sparkline(&row.rates, 20),
+                quantile(0.5),
+                quantile(0.9),
+                quantile(0.99),
+            ]);
+        }
+        let mut widths = [0; 9];
+        for cells in &table {
+            for (width, cell) in widths.iter_mut().zip(cells) {
+                *width = (*width).max(cell.chars().count()).min(40);
+            }
+        }
+
+        let mut lines = vec![status];
+        for cells in &table {
+            let mut line = String::new();
+            for (i, cell) in cells.iter().enumerate() {
+                let cell: String = cell.chars().take(widths[i]).collect();
+                // Numbers are right-aligned.
+                if (3..5).contains(&i) || i >= 6 {
+                    line.push_str(&format!("{:>width$}  ", cell, width = widths[i]));
+                } else {
+                    line.push_str(&format!("{:width$}  ", cell, width = widths[i]));
+                }
+            }
+            lines.push(line.trim_end().to_string());
+        }
+        lines.push("q quit  s sort  r reverse  / filter  esc clear filter".to_string());
+        for line in &mut lines {
+            if line.chars().count() > width {
+                *line = line.chars().take(width).collect();
+            }
+        }
+        lines
+    }
+}
+
+fn number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else if value.abs() >= 100.0 {
+        format!("{:.0}", value)
+    } else {
+        format!("{:.2}", value)
+    }
+}
+
+/// The last `width` values as bars scaled between their minimum (or 0) and
+/// maximum.
+fn sparkline(values: &VecDeque<f64>, width: usize) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let values: Vec<f64> = values
+        .iter()
+        .skip(values.len().saturating_sub(width))
+        .copied()
+        .collect();
+    let min = values.iter().copied().fold(0.0, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    values
+        .iter()
+        .map(|value| {
+            if max > min {
+                BARS[((value - min) / (max - min) * 7.0).round() as usize]
+            } else {
+                BARS[0]
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        aggregation::Aggregation,
+        exporter::prometheus::{self, Format},
+        meter_provider::MeterProvider,
+        metric::Temporality,
+        view::View,
+    };
+
+    fn provider() -> (MeterProvider, ManualReader) {
+        let reader = ManualReader::new(Temporality::Cumulative);
+        let provider = MeterProvider::builder()
+            .with_reader(reader.clone())
+            .with_view(
+                View::new("latency")
+                    .with_aggregation(Aggregation::explicit_histogram(vec![10.0, 100.0])),
+            )
+            .build()
+            .unwrap();
+        (provider, reader)
+    }
+
+    #[test]
+    fn reads_prometheus_exposition() {
+        let (provider, reader) = provider();
+        let meter = provider.get_meter("http");
+        meter
+            .create_counter("requests")
+            .add(3, &[KeyValue::new("route", "/a \"b\"")]);
+        let latency = meter.create_counter("latency");
+        for value in [5, 50, 500] {
+            latency.add(value, &[]);
+        }
+        let metrics = reader.collect().unwrap();
+
+        let mut scraped = parse_exposition(&prometheus::encode(&metrics, Format::Text));
+        let mut collected = samples(&metrics);
+        let key = |sample: &Sample| (sample.meter.clone(), sample.name.clone());
+        scraped.sort_by_key(key);
+        collected.sort_by_key(key);
+        assert_eq!(scraped, collected);
+        assert_eq!(scraped[1].attributes, "route=/a \"b\"");
+        assert_eq!(
+            scraped[0].histogram,
+            Some(Buckets {
+                bounds: vec![10.0, 100.0],
+                counts: vec![1, 1, 1],
+            })
+        );
+    }
+
+    #[test]
+    fn estimates_quantiles() {
+        let buckets = Buckets {
+            bounds: vec![10.0, 100.0],
+            counts: vec![50, 40, 10],
+        };
+        assert_eq!(buckets.quantile(0.5), Some(10.0));
+        assert_eq!(buckets.quantile(0.7), Some(55.0));
+        assert_eq!(buckets.quantile(0.99), Some(100.0));
+        assert_eq!(buckets.quantile(0.25), Some(5.0));
+    }
+
+    #[test]
+    fn renders_rates_sorted_and_filtered() {
+        let (provider, reader) = provider();
+        let requests = provider.get_meter("http").create_counter("requests");
+        let mut dashboard = Dashboard::new("in-process");
+        let (route_a, route_b) = (
+            [KeyValue::new("route", "/a")],
+            [KeyValue::new("route", "/b")],
+        );
+        requests.add(10, &route_a);
+        requests.add(10, &route_b);
+        dashboard.update(samples(&reader.collect().unwrap()), None);
+        requests.add(2, &route_a);
+        requests.add(8, &route_b);
+        let elapsed = Some(Duration::from_secs(2));
+        dashboard.update(samples(&reader.collect().unwrap()), elapsed);
+
+        let key = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+        dashboard.handle_key(key('s'));
+        dashboard.handle_key(key('s'));
+        assert_eq!(
+            dashboard.render(80, 10),
+            [
+                "metrics-top  in-process  2/2 series  sort: rate",
+                "METER  INSTRUMENT  ATTRIBUTES  VALUE  RATE/s  TREND  P50  P90  P99",
+                "http   requests    route=/b       18       4  █",
+                "http   requests    route=/a       12       1  █",
+                "q quit  s sort  r reverse  / filter  esc clear filter",
+            ]
+        );
+
+        for c in "//a".chars() {
+            dashboard.handle_key(key(c));
+        }
+        let lines = dashboard.render(80, 10);
+        assert!(lines[0].ends_with("1/2 series  sort: rate  filter: /a_"));
+        assert!(lines[2].contains("route=/a"));
+        // Keys are typed into the filter until it is closed.
+        assert!(dashboard.handle_key(key('q')));
+        dashboard.handle_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        assert_eq!(dashboard.visible().len(), 2);
+        assert!(!dashboard.handle_key(key('q')));
+    }
+}
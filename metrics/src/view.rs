@@ -0,0 +1,333 @@
+use std::collections::HashSet;
+
+use crate::aggregation::Aggregation;
+use crate::common::{Key, KeyValue};
+use crate::error::{MetricsError, MetricsResult};
+use crate::instrument::{Instrument, InstrumentKind};
+
+/// Selects instruments and customizes the metric stream they produce.
+///
+/// A view matches an instrument when every configured criterion matches. The
+/// instrument name pattern supports `*` (any run of characters) and `?` (any
+/// single character).
+///
+/// ```
+/// use metrics::aggregation::Aggregation;
+/// use metrics::view::View;
+///
+/// let view = View::new("http.server.*")
+///     .with_meter_name("hyper")
+///     .with_aggregation(Aggregation::explicit_histogram(vec![10.0, 100.0]));
+/// ```
+#[derive(Clone, Debug)]
+pub struct View {
+    instrument_name: String,
+    meter_name: Option<String>,
+    kind: Option<InstrumentKind>,
+    name: Option<String>,
+    description: Option<String>,
+    aggregation: Option<Aggregation>,
+    attribute_filter: AttributeFilter,
+}
+
+impl View {
+    /// Creates a view matching instruments whose name matches `instrument_name`.
+    pub fn new(instrument_name: &str) -> View {
+        View {
+            instrument_name: instrument_name.to_string(),
+            meter_name: None,
+            kind: None,
+            name: None,
+            description: None,
+            aggregation: None,
+            attribute_filter: AttributeFilter::All,
+        }
+    }
+
+    /// Only match instruments created by the meter with this name.
+    pub fn with_meter_name(mut self, meter_name: &str) -> View {
+        self.meter_name = Some(meter_name.to_string());
+        self
+    }
+
+    /// Only match instruments of this kind.
+    pub fn with_kind(mut self, kind: InstrumentKind) -> View {
+        self.kind = Some(kind);
+        self
+    }
+
+    /// Rename the resulting metric stream.
+    pub fn with_name(mut self, name: &str) -> View {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// Replace the description of the resulting metric stream.
+    pub fn with_description(mut self, description: &str) -> View {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    /// Aggregate measurements with `aggregation` instead of the default.
+    pub fn with_aggregation(mut self, aggregation: Aggregation) -> View {
+        self.aggregation = Some(aggregation);
+        self
+    }
+
+    /// Keep only attributes with these keys; measurements that differ only in
+    /// other attributes are aggregated into the same point.
+    pub fn with_allowed_attribute_keys<K: Into<Key>>(
+        mut self,
+        keys: impl IntoIterator<Item = K>,
+    ) -> View {
+        self.attribute_filter = AttributeFilter::Allow(keys.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Drop attributes with these keys before aggregating.
+    pub fn with_denied_attribute_keys<K: Into<Key>>(
+        mut self,
+        keys: impl IntoIterator<Item = K>,
+    ) -> View {
+        self.attribute_filter = AttributeFilter::Deny(keys.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub(crate) fn validate(&self) -> MetricsResult<()> {
+        if self.name.is_some() && has_wildcard(&self.instrument_name) {
+            return Err(MetricsError::InvalidView(format!(
+                "view for '{}' renames the stream but may match several instruments",
+                self.instrument_name
+            )));
+        }
+        if let Some(aggregation) = &self.aggregation {
+            aggregation.validate()?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn matches(&self, instrument: &Instrument) -> bool {
+        if let Some(kind) = self.kind {
+            if kind != instrument.kind {
+                return false;
+            }
+        }
+        if let Some(meter_name) = &self.meter_name {
+            if *meter_name != instrument.meter_name {
+                return false;
+            }
+        }
+        wildcard_match(&self.instrument_name, &instrument.name)
+    }
+
+    /// Builds the stream this view produces for `instrument`. `default_aggregation`
+    /// is the reader's preference, used when the view does not set one.
+    pub(crate) fn stream(
+        &self,
+        instrument: &Instrument,
+        default_aggregation: &Aggregation,
+    ) -> Stream {
+        Stream {
+            name: self.name.clone().unwrap_or_else(|| instrument.name.clone()),
+            description: self
+                .description
+                .clone()
+                .unwrap_or_else(|| instrument.description.clone()),
+            unit: instrument.unit.clone(),
+            aggregation: self
+                .aggregation
+                .as_ref()
+                .unwrap_or(default_aggregation)
+                .resolve(instrument.kind),
+            attribute_filter: self.attribute_filter.clone(),
+        }
+    }
+}
+
+/// Restricts which attribute keys a stream keeps.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum AttributeFilter {
+    All,
+    Allow(HashSet<Key>),
+    Deny(HashSet<Key>),
+}
+
+impl AttributeFilter {
+    pub(crate) fn is_all(&self) -> bool {
+        matches!(self, AttributeFilter::All)
+    }
+
+    pub(crate) fn apply(&self, attributes: &[KeyValue]) -> Vec<KeyValue> {
+        attributes
+            .iter()
+            .filter(|kv| match self {
+                AttributeFilter::All => true,
+                AttributeFilter::Allow(keys) => keys.contains(&kv.key),
+                AttributeFilter::Deny(keys) => !keys.contains(&kv.key),
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+/// The resolved shape of one metric stream produced by an instrument.
+#[derive(Clone, Debug)]
+pub(crate) struct Stream {
+    pub(crate) name: String,
+    pub(crate) description: String,
+    pub(crate) unit: String,
+    pub(crate) aggregation: Aggregation,
+    pub(crate) attribute_filter: AttributeFilter,
+}
+
+impl Stream {
+    /// The stream an instrument produces when no view matches it.
+    pub(crate) fn default_for(
+        instrument: &Instrument,
+        default_aggregation: &Aggregation,
+    ) -> Stream {
+        Stream {
+            name: instrument.name.clone(),
+            description: instrument.description.clone(),
+            unit: instrument.unit.clone(),
+            aggregation: default_aggregation.resolve(instrument.kind),
+            attribute_filter: AttributeFilter::All,
+        }
+    }
+}
+
+/// Resolves the streams `instrument` produces under `views`. Streams with the
+/// `Drop` aggregation are omitted.
+pub(crate) fn streams_for(
+    instrument: &Instrument,
+    views: &[View],
+    default_aggregation: &Aggregation,
+) -> Vec<Stream> {
+    let mut matched = false;
+    let mut streams = vec![];
+    for view in views.iter().filter(|view| view.matches(instrument)) {
+        matched = true;
+        streams.push(view.stream(instrument, default_aggregation));
+    }
+    if !matched {
+        streams.push(Stream::default_for(instrument, default_aggregation));
+    }
+    streams.retain(|stream| stream.aggregation != Aggregation::Drop);
+    streams
+}
+
+fn has_wildcard(pattern: &str) -> bool {
+    pattern.contains(['*', '?'])
+}
+
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            n = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counter(name: &str, meter_name: &str) -> Instrument {
+        Instrument {
+            name: name.to_string(),
+            description: String::new(),
+            unit: String::new(),
+            kind: InstrumentKind::Counter,
+            meter_name: meter_name.to_string(),
+        }
+    }
+
+    #[test]
+    fn wildcards() {
+        assert!(wildcard_match("*", "anything"));
+        assert!(wildcard_match("http.*", "http.requests"));
+        assert!(wildcard_match("http.?equests", "http.requests"));
+        assert!(wildcard_match("*.requests", "http.requests"));
+        assert!(!wildcard_match("http.*", "grpc.requests"));
+        assert!(!wildcard_match("requests", "requests2"));
+    }
+
+    #[test]
+    fn unmatched_instrument_gets_default_stream() {
+        let views = [View::new("other").with_name("renamed")];
+        let streams = streams_for(&counter("requests", "meter"), &views, &Aggregation::Default);
+        assert_eq!(streams.len(), 1);
+        assert_eq!(streams[0].name, "requests");
+        assert_eq!(streams[0].aggregation, Aggregation::Sum);
+    }
+
+    #[test]
+    fn each_matching_view_adds_a_stream() {
+        let views = [
+            View::new("requests").with_name("renamed"),
+            View::new("req*").with_aggregation(Aggregation::LastValue),
+            View::new("requests").with_meter_name("other-meter"),
+            View::new("*").with_aggregation(Aggregation::Drop),
+        ];
+        let streams = streams_for(&counter("requests", "meter"), &views, &Aggregation::Default);
+        assert_eq!(streams.len(), 2);
+        assert_eq!(streams[0].name, "renamed");
+        assert_eq!(streams[1].aggregation, Aggregation::LastValue);
+    }
+
+    #[test]
+    fn reader_aggregation_applies_only_without_view_aggregation() {
+        let views = [
+            View::new("requests").with_aggregation(Aggregation::Sum),
+            View::new("requests").with_name("requests.last"),
+        ];
+        let streams = streams_for(
+            &counter("requests", "meter"),
+            &views,
+            &Aggregation::LastValue,
+        );
+        assert_eq!(streams[0].aggregation, Aggregation::Sum);
+        assert_eq!(streams[1].aggregation, Aggregation::LastValue);
+    }
+
+    #[test]
+    fn attribute_filters() {
+        let attributes = [
+            KeyValue::new("route", "/a"),
+            KeyValue::new("user", "42"),
+            KeyValue::new("status", 200),
+        ];
+        let allow = View::new("*").with_allowed_attribute_keys(["route", "status"]);
+        let deny = View::new("*").with_denied_attribute_keys(["user"]);
+        let instrument = counter("requests", "meter");
+        for view in [allow, deny] {
+            let stream = view.stream(&instrument, &Aggregation::Default);
+            let kept = stream.attribute_filter.apply(&attributes);
+            assert_eq!(kept, vec![attributes[0].clone(), attributes[2].clone()]);
+        }
+    }
+
+    #[test]
+    fn rename_with_wildcard_is_rejected() {
+        assert!(View::new("http.*").with_name("x").validate().is_err());
+        assert!(View::new("http")
+            .with_aggregation(Aggregation::explicit_histogram(vec![2.0, 1.0]))
+            .validate()
+            .is_err());
+    }
+}